@@ -1,7 +1,7 @@
 use node_template_runtime::{
     pallet_utxo, AccountId, BalancesConfig, GenesisConfig, PpConfig, SessionConfig, Signature,
-    StakerStatus, StakingConfig, SudoConfig, SystemConfig, UtxoConfig, MINIMUM_STAKE,
-    NUM_OF_VALIDATOR_SLOTS, WASM_BINARY,
+    StakerStatus, StakingConfig, SudoConfig, SystemConfig, UtxoConfig, MINIMUM_NOMINATOR_STAKE,
+    MINIMUM_STAKE, NUM_OF_VALIDATOR_SLOTS, WASM_BINARY,
 };
 use sc_network::config::MultiaddrWithPeerId;
 use sc_service::ChainType;
@@ -293,6 +293,9 @@ fn testnet_genesis(
             invulnerables: initial_authorities.iter().map(|x| x.controller_account_id()).collect(),
             slash_reward_fraction: sp_runtime::Perbill::from_percent(0), // nothing, since we're not using this at all.
             stakers,
+            // the same minimums are checked by the `pallet-utxo` before bonding.
+            min_nominator_bond: MINIMUM_NOMINATOR_STAKE,
+            min_validator_bond: MINIMUM_STAKE,
             ..Default::default()
         },
     }
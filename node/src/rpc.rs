@@ -33,11 +33,13 @@ where
     C::Api: substrate_frame_rpc_system::AccountNonceApi<Block, AccountId, Index>,
     C::Api: pallet_transaction_payment_rpc::TransactionPaymentRuntimeApi<Block, Balance>,
     C::Api: pallet_utxo_rpc::UtxoRuntimeApi<Block>,
+    C::Api: pallet_pp_rpc::PpRuntimeApi<Block, AccountId>,
     C::Api: pallet_contracts_rpc::ContractsRuntimeApi<Block, AccountId, Balance, BlockNumber, Hash>,
     C::Api: BlockBuilder<Block>,
     P: TransactionPool + 'static,
 {
     use pallet_contracts_rpc::{Contracts, ContractsApi};
+    use pallet_pp_rpc::{Pp, PpApi};
     use pallet_transaction_payment_rpc::{TransactionPayment, TransactionPaymentApi};
     use pallet_utxo_rpc::{Utxo, UtxoApi};
     use substrate_frame_rpc_system::{FullSystem, SystemApi};
@@ -60,6 +62,7 @@ where
     )));
 
     io.extend_with(UtxoApi::to_delegate(Utxo::new(client.clone())));
+    io.extend_with(PpApi::to_delegate(Pp::new(client.clone())));
     io.extend_with(ContractsApi::to_delegate(Contracts::new(client.clone())));
 
     // Extend this RPC with a custom API by using the following syntax.
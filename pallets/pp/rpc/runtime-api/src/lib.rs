@@ -0,0 +1,43 @@
+// Copyright (c) 2021 RBB S.r.l
+// opensource@mintlayer.org
+// SPDX-License-Identifier: MIT
+// Licensed under the MIT License;
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// 	http://spdx.org/licenses/MIT
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+#![cfg_attr(not(feature = "std"), no_std)]
+
+use codec::Codec;
+use frame_support::{dispatch::DispatchError, inherent::Vec, weights::Weight};
+use pallet_pp::{ContractBalance, DryRunResult};
+use sp_core::H256;
+
+sp_api::decl_runtime_apis! {
+    pub trait PpApi<AccountId> where AccountId: Codec {
+        /// Returns the funds, UTXOs and tokens of the smart contract, if it exists.
+        fn contract_balance(contract: AccountId) -> Option<ContractBalance>;
+
+        /// Returns all the smart contracts created by `CreatePP` UTXOs,
+        /// with the outpoint that created each of them.
+        fn contracts() -> Vec<(AccountId, H256)>;
+
+        /// Simulates a `CallPP` UTXO paying `value` to the smart contract `dest`,
+        /// funding included, and the call to the contract with `input_data`,
+        /// without committing anything.
+        fn dry_run_call(
+            caller: AccountId,
+            dest: AccountId,
+            value: u128,
+            fund_contract: bool,
+            input_data: Vec<u8>,
+            gas_limit: Weight,
+        ) -> Result<DryRunResult, DispatchError>;
+    }
+}
@@ -0,0 +1,160 @@
+// Copyright (c) 2021 RBB S.r.l
+// opensource@mintlayer.org
+// SPDX-License-Identifier: MIT
+// Licensed under the MIT License;
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// 	http://spdx.org/licenses/MIT
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use codec::Codec;
+use jsonrpc_core::{Error as RpcError, ErrorCode, Result};
+use jsonrpc_derive::rpc;
+use pallet_pp::{ContractBalance, DryRunResult};
+pub use pallet_pp_rpc_runtime_api::PpApi as PpRuntimeApi;
+use sp_api::ProvideRuntimeApi;
+use sp_blockchain::HeaderBackend;
+use sp_core::{Bytes, H256};
+use sp_runtime::{generic::BlockId, traits::Block as BlockT};
+use std::sync::Arc;
+
+#[rpc]
+pub trait PpApi<BlockHash, AccountId> {
+    /// Returns the funds of the smart contract, the UTXOs it owns with their values,
+    /// and the funds and UTXOs of each token it holds.
+    /// `None` if the smart contract doesn't exist.
+    #[rpc(name = "pp_contractBalance")]
+    fn contract_balance(
+        &self,
+        contract: AccountId,
+        at: Option<BlockHash>,
+    ) -> Result<Option<ContractBalance>>;
+
+    /// Returns all the smart contracts created by `CreatePP` UTXOs,
+    /// with the outpoint that created each of them.
+    #[rpc(name = "pp_contracts")]
+    fn contracts(&self, at: Option<BlockHash>) -> Result<Vec<(AccountId, H256)>>;
+
+    /// Simulates a `CallPP` UTXO paying `value` to the smart contract `dest`,
+    /// funding included, and the call to the contract with `input_data`.
+    /// Returns the gas consumed, the data returned, the debug buffer and the
+    /// SCALE-encoded UTXO transactions made by smart contracts. Nothing is committed.
+    #[rpc(name = "pp_dryRunCall")]
+    fn dry_run_call(
+        &self,
+        caller: AccountId,
+        dest: AccountId,
+        value: u128,
+        fund_contract: bool,
+        input_data: Bytes,
+        gas_limit: u64,
+        at: Option<BlockHash>,
+    ) -> Result<DryRunResult>;
+}
+
+/// A struct that implements the [`PpApi`].
+pub struct Pp<C, M> {
+    client: Arc<C>,
+    _marker: std::marker::PhantomData<M>,
+}
+
+impl<C, M> Pp<C, M> {
+    /// Create new `Pp` instance with the given reference to the client.
+    pub fn new(client: Arc<C>) -> Self {
+        Self {
+            client,
+            _marker: Default::default(),
+        }
+    }
+}
+
+/// Error type of this RPC API.
+pub enum Error {
+    /// The call to runtime failed.
+    RuntimeError = 1,
+    /// The dry run failed before calling the smart contract.
+    DryRunFailed = 2,
+}
+
+impl<C, Block, AccountId> PpApi<<Block as BlockT>::Hash, AccountId> for Pp<C, Block>
+where
+    Block: BlockT,
+    AccountId: Codec,
+    C: Send + Sync + 'static,
+    C: ProvideRuntimeApi<Block>,
+    C: HeaderBackend<Block>,
+    C::Api: PpRuntimeApi<Block, AccountId>,
+{
+    fn contract_balance(
+        &self,
+        contract: AccountId,
+        at: Option<<Block as BlockT>::Hash>,
+    ) -> Result<Option<ContractBalance>> {
+        let api = self.client.runtime_api();
+        let at = BlockId::hash(at.unwrap_or_else(||
+            // If the block hash is not supplied assume the best block.
+            self.client.info().best_hash));
+
+        api.contract_balance(&at, contract).map_err(|e| RpcError {
+            code: ErrorCode::ServerError(Error::RuntimeError as i64),
+            message: "Unable to query the contract balance.".into(),
+            data: Some(format!("{:?}", e).into()),
+        })
+    }
+
+    fn contracts(&self, at: Option<<Block as BlockT>::Hash>) -> Result<Vec<(AccountId, H256)>> {
+        let api = self.client.runtime_api();
+        let at = BlockId::hash(at.unwrap_or_else(||
+            // If the block hash is not supplied assume the best block.
+            self.client.info().best_hash));
+
+        api.contracts(&at).map_err(|e| RpcError {
+            code: ErrorCode::ServerError(Error::RuntimeError as i64),
+            message: "Unable to query the contracts.".into(),
+            data: Some(format!("{:?}", e).into()),
+        })
+    }
+
+    fn dry_run_call(
+        &self,
+        caller: AccountId,
+        dest: AccountId,
+        value: u128,
+        fund_contract: bool,
+        input_data: Bytes,
+        gas_limit: u64,
+        at: Option<<Block as BlockT>::Hash>,
+    ) -> Result<DryRunResult> {
+        let api = self.client.runtime_api();
+        let at = BlockId::hash(at.unwrap_or_else(||
+            // If the block hash is not supplied assume the best block.
+            self.client.info().best_hash));
+
+        let result = api
+            .dry_run_call(
+                &at,
+                caller,
+                dest,
+                value,
+                fund_contract,
+                input_data.to_vec(),
+                gas_limit,
+            )
+            .map_err(|e| RpcError {
+                code: ErrorCode::ServerError(Error::RuntimeError as i64),
+                message: "Unable to dry run the contract call.".into(),
+                data: Some(format!("{:?}", e).into()),
+            })?;
+        result.map_err(|e| RpcError {
+            code: ErrorCode::ServerError(Error::DryRunFailed as i64),
+            message: "The dry run of the contract call failed.".into(),
+            data: Some(format!("{:?}", e).into()),
+        })
+    }
+}
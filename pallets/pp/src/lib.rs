@@ -27,35 +27,44 @@ mod tests;
 #[cfg(feature = "runtime-benchmarks")]
 mod benchmarking;
 
-use codec::Encode;
+use codec::{Decode, Encode};
 pub use frame_support::{
     construct_runtime,
     dispatch::Vec,
     ensure, parameter_types,
-    sp_runtime::{DispatchError, SaturatedConversion},
+    sp_runtime::{traits::Hash, DispatchError, SaturatedConversion},
     traits::{
-        Currency, Everything, IsSubType, KeyOwnerProofSystem, LockableCurrency, Nothing,
+        Currency, Everything, Get, IsSubType, KeyOwnerProofSystem, LockableCurrency, Nothing,
         Randomness, ReservableCurrency,
     },
     weights::{
-        constants::{BlockExecutionWeight, ExtrinsicBaseWeight, RocksDbWeight, WEIGHT_PER_SECOND},
+        constants::{
+            BlockExecutionWeight, ExtrinsicBaseWeight, RocksDbWeight, WEIGHT_PER_MICROS,
+            WEIGHT_PER_NANOS, WEIGHT_PER_SECOND,
+        },
         Weight,
     },
     BoundedVec, StorageValue,
 };
+use frame_support::{sp_io::crypto, sp_std::collections::btree_map::BTreeMap};
 use pallet_contracts::chain_extension::{
     ChainExtension, Environment, Ext, InitState, RetVal, SysConfig,
 };
+use pallet_contracts_primitives::{ContractAccessError, ContractExecResult, ReturnFlags};
 use pp_api::ProgrammablePoolApi;
-use sp_core::{crypto::UncheckedFrom, Bytes, H256};
-use utxo_api::UtxoApi;
+use sp_core::{crypto::UncheckedFrom, sr25519, Bytes, H160, H256, H512};
+use utxo_api::{Beneficiary, UtxoApi};
 
 #[frame_support::pallet]
 pub mod pallet {
     use frame_support::inherent::Vec;
     use frame_support::pallet_prelude::*;
+    use frame_support::sp_std::collections::btree_map::BTreeMap;
     use frame_system::pallet_prelude::*;
-    use sp_core::{H256, H512};
+    use pallet_contracts_primitives::ContractExecResult;
+    #[cfg(feature = "std")]
+    use serde::{Deserialize, Serialize};
+    use sp_core::{Bytes, H160, H256, H512};
     use utxo_api::UtxoApi;
 
     #[pallet::config]
@@ -64,10 +73,31 @@ pub mod pallet {
         type Utxo: UtxoApi<AccountId = Self::AccountId>;
     }
 
+    #[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
     #[derive(Clone, Encode, Decode, Eq, PartialEq, PartialOrd, Ord, RuntimeDebug)]
     pub struct ContractBalance {
         pub funds: u128,
         pub utxos: Vec<(H256, u128)>,
+        pub tokens: BTreeMap<H160, TokenBalance>,
+    }
+
+    /// The funds of a smart contract in a token, and the UTXOs holding the token
+    /// with their token amounts.
+    #[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
+    #[derive(Clone, Encode, Decode, Eq, PartialEq, PartialOrd, Ord, RuntimeDebug, Default)]
+    pub struct TokenBalance {
+        pub funds: u128,
+        pub utxos: Vec<(H256, u128)>,
+    }
+
+    /// The outcome of a dry run of a smart contract call.
+    #[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
+    #[derive(Encode, Decode, Eq, PartialEq, RuntimeDebug)]
+    pub struct DryRunResult {
+        /// The gas consumed, the data returned and the debug buffer of the call.
+        pub exec_result: ContractExecResult,
+        /// The SCALE-encoded UTXO transactions made by smart contracts during the call.
+        pub transactions: Vec<Bytes>,
     }
 
     #[pallet::pallet]
@@ -79,9 +109,34 @@ pub mod pallet {
     pub(super) type ContractBalances<T: Config> =
         StorageMap<_, Identity, T::AccountId, Option<ContractBalance>, ValueQuery>;
 
+    /// The outpoint of the `CreatePP` UTXO that created each smart contract.
+    #[pallet::storage]
+    #[pallet::getter(fn contract_creations)]
+    pub(super) type ContractCreations<T: Config> =
+        StorageMap<_, Identity, T::AccountId, H256, OptionQuery>;
+
     #[pallet::event]
-    #[pallet::metadata(T::AccountId = "AccountId")]
-    pub enum Event<T: Config> {}
+    #[pallet::generate_deposit(pub(super) fn deposit_event)]
+    #[pallet::metadata(T::AccountId = "AccountId", T::Hash = "Hash")]
+    pub enum Event<T: Config> {
+        /// A smart contract has been instantiated from the code with `code_hash`.
+        /// \[contract_account, code_hash, utxo\]
+        ContractInstantiated(T::AccountId, T::Hash, H256),
+
+        /// A smart contract has been called, or only funded if there was no input data.
+        /// Only successful calls are recorded: a call that fails, or that the contract
+        /// reverts, fails the whole transaction, and this event with it.
+        /// \[caller, contract_account, utxo, value\]
+        ContractCalled(T::AccountId, T::AccountId, H256, u128),
+
+        /// A smart contract has spent its UTXOs, creating new ones.
+        /// \[contract_account, new_utxos\]
+        ContractPaidOut(T::AccountId, Vec<H256>),
+
+        /// A smart contract has been terminated, after sweeping all its UTXOs to a beneficiary.
+        /// \[contract_account, new_utxos\]
+        ContractTerminated(T::AccountId, Vec<H256>),
+    }
 
     #[pallet::hooks]
     impl<T: Config> Hooks<BlockNumberFor<T>> for Pallet<T> {}
@@ -124,11 +179,12 @@ pub mod pallet {
 /// and send it by calling into the UTXO system.
 ///
 /// UTXO system implements the consensus-critical coin-picking
-/// algorithm by condensing all vins into one transaction and
+/// algorithm by condensing the picked vins into one transaction and
 /// using the outpoint in asceding order to select the place
 /// for each vin in the array of inputs. This ensures that all
 /// PP validator nodes that execute the transaction output the
-/// exact same TX.
+/// exact same TX. What is left of the picked vins goes back to
+/// the smart contract as change.
 ///
 /// # Arguments
 /// * `caller` - Smart contract's account id
@@ -138,38 +194,285 @@ fn send_p2pk_tx<T: Config>(
     caller: &T::AccountId,
     dest: &T::AccountId,
     value: u128,
-) -> Result<(), DispatchError> {
-    let fund_info =
-        <ContractBalances<T>>::get(&caller).ok_or(DispatchError::Other("Caller doesn't exist"))?;
-    ensure!(fund_info.funds >= value, "Caller doesn't have enough funds");
-    let outpoints = fund_info.utxos.iter().map(|x| x.0).collect::<Vec<H256>>();
+) -> Result<Weight, DispatchError> {
+    let outpoints = pick_utxos::<T>(caller, value)?;
+    let new_utxos = T::Utxo::send_conscrit_p2pk(caller, dest, value, &outpoints)?;
+    let spend_weight = conscrit_tx_weight::<T>(outpoints.len(), new_utxos.len());
 
-    T::Utxo::send_conscrit_p2pk(caller, dest, value, &outpoints)
+    Pallet::<T>::deposit_event(Event::<T>::ContractPaidOut(caller.clone(), new_utxos));
+    Ok(spend_weight)
 }
 
 /// Create Contract-to-Contract transfer that allows smart contracts to
 /// call each other and transfer funds through the UTXO system.
 ///
 /// UTXO system converts this high-level transaction request from
-/// `caller` to `dest` to an actual transaction, uses OP_SPEND
-/// to unlock the funds of the UTXOs that the `caller` has
-/// acquired and creates a new vout with `data` that
-/// calls `dest` and transfers all funds of `caller` to this smart contract
+/// `caller` to `dest` to an actual transaction, spends on behalf
+/// of `caller` the UTXOs that it has acquired (no one else can)
+/// and creates a new vout with `data` that
+/// calls `dest` and transfers `value` to this smart contract.
+/// What is left of the picked UTXOs goes back to `caller` as change.
 ///
 /// * `caller` -  Smart contract that is doing the calling
 /// * `dest` - Smart contract that is to be called
+/// * `value` - How much is tranferred to `dest`
 /// * `data` - Selector and all other data `dest` takes as input
+/// * `gas_limit` - The most gas `dest` may consume
 fn send_c2c_tx<T: Config>(
     caller: &T::AccountId,
     dest: &T::AccountId,
+    value: u128,
     data: &Vec<u8>,
+    gas_limit: Weight,
+) -> Result<Weight, DispatchError> {
+    let outpoints = pick_utxos::<T>(caller, value)?;
+
+    let (gas_consumed, new_utxos) =
+        T::Utxo::send_conscrit_c2c(caller, dest, value, data, &outpoints, gas_limit)?;
+    let spend_weight = conscrit_tx_weight::<T>(outpoints.len(), new_utxos.len());
+
+    Pallet::<T>::deposit_event(Event::<T>::ContractPaidOut(caller.clone(), new_utxos));
+    Ok(gas_consumed.saturating_add(spend_weight))
+}
+
+/// Create Contract-to-Contract transfer that moves `value` from
+/// the UTXOs of `caller` to `dest`, without calling `dest`.
+///
+/// * `caller` - Smart contract that is transferring the funds
+/// * `dest` - Smart contract that receives the funds
+/// * `value` - How much is tranferred to `dest`
+fn send_c2c_transfer<T: Config>(
+    caller: &T::AccountId,
+    dest: &T::AccountId,
+    value: u128,
+) -> Result<Weight, DispatchError> {
+    let outpoints = pick_utxos::<T>(caller, value)?;
+
+    // without input data, `dest` is only funded and there is no gas to pay for
+    let (_, new_utxos) =
+        T::Utxo::send_conscrit_c2c(caller, dest, value, &Vec::new(), &outpoints, 0)?;
+    let spend_weight = conscrit_tx_weight::<T>(outpoints.len(), new_utxos.len());
+
+    Pallet::<T>::deposit_event(Event::<T>::ContractPaidOut(caller.clone(), new_utxos));
+    Ok(spend_weight)
+}
+
+/// Create a transaction that sends `amount` of the token with `token_id` from
+/// the UTXOs of `caller` to `dest`, which is either funded without being called
+/// if it is a smart contract, or paid to as a public key otherwise.
+///
+/// * `caller` - Smart contract that is transferring the tokens
+/// * `dest` - Recipient of the tokens
+/// * `token_id` - The token to transfer
+/// * `amount` - How much of the token is transferred to `dest`
+fn send_token_tx<T: Config>(
+    caller: &T::AccountId,
+    dest: &T::AccountId,
+    token_id: &H160,
+    amount: u128,
+) -> Result<Weight, DispatchError> {
+    let outpoints = pick_token_utxos::<T>(caller, token_id, amount)?;
+
+    let new_utxos = if <ContractBalances<T>>::get(dest).is_some() {
+        T::Utxo::send_conscrit_token_c2c(caller, dest, token_id, amount, &outpoints)?
+    } else {
+        T::Utxo::send_conscrit_token_p2pk(caller, dest, token_id, amount, &outpoints)?
+    };
+    let spend_weight = conscrit_tx_weight::<T>(outpoints.len(), new_utxos.len());
+
+    Pallet::<T>::deposit_event(Event::<T>::ContractPaidOut(caller.clone(), new_utxos));
+    Ok(spend_weight)
+}
+
+/// Sweeps all UTXOs of the smart contract, in MLT and in tokens, to `beneficiary`
+/// and removes the balance of the contract, which is about to be terminated.
+///
+/// * `contract` - Smart contract that is being terminated
+/// * `beneficiary` - Public key or script hash that receives everything the contract owns
+fn sweep_utxos<T: Config>(
+    contract: &T::AccountId,
+    beneficiary: &Beneficiary,
 ) -> Result<(), DispatchError> {
-    let fund_info = <ContractBalances<T>>::get(caller).ok_or(DispatchError::Other(
-        "Contract doesn't own any UTXO or it doesn't exist!",
-    ))?;
-    let outpoints = fund_info.utxos.iter().map(|x| x.0).collect::<Vec<H256>>();
+    let fund_info = <ContractBalances<T>>::get(contract)
+        .ok_or(DispatchError::Other("Contract doesn't exist!"))?;
+    let outpoints: Vec<H256> = fund_info.utxos.iter().map(|(outpoint, _)| *outpoint).collect();
+
+    let new_utxos = if outpoints.is_empty() {
+        Vec::new()
+    } else {
+        T::Utxo::send_conscrit_sweep(contract, beneficiary, &outpoints)?
+    };
+    <ContractBalances<T>>::remove(contract);
+    <ContractCreations<T>>::remove(contract);
+
+    Pallet::<T>::deposit_event(Event::<T>::ContractTerminated(contract.clone(), new_utxos));
+    Ok(())
+}
+
+/// Weight of the storage accesses of a transaction made by a smart contract:
+/// each input is read and removed, each output is written, and the balance
+/// of the contract that owned or receives it is read and updated.
+fn conscrit_tx_weight<T: Config>(inputs: usize, outputs: usize) -> Weight {
+    let utxos = inputs.saturating_add(outputs) as Weight;
+    T::DbWeight::get().reads_writes(utxos.saturating_mul(2), utxos.saturating_mul(2))
+}
+
+/// Whether the smart contract has been removed from `pallet_contracts`.
+fn is_terminated<T: Config>(contract: &T::AccountId) -> bool
+where
+    T::AccountId: UncheckedFrom<T::Hash> + AsRef<[u8]>,
+{
+    matches!(
+        pallet_contracts::Pallet::<T>::get_storage(contract.clone(), [0u8; 32]),
+        Err(ContractAccessError::DoesntExist)
+    )
+}
+
+/// A smart contract that has been removed from `pallet_contracts` without sweeping
+/// its UTXOs, e.g. with `seal_terminate`. Every contract is checked, as any of them
+/// may have been reached through a nested `seal_call`.
+fn terminated_without_sweeping<T: Config>() -> Option<T::AccountId>
+where
+    T::AccountId: UncheckedFrom<T::Hash> + AsRef<[u8]>,
+{
+    <ContractBalances<T>>::iter_keys().find(|contract| is_terminated::<T>(contract))
+}
 
-    T::Utxo::send_conscrit_c2c(caller, dest, fund_info.funds, data, &outpoints)
+/// Adds the UTXO paid to the smart contract `dest` to its balance information,
+/// and to its funds if `fund_contract` is set.
+fn add_utxo<T: Config>(
+    dest: &T::AccountId,
+    utxo_hash: H256,
+    utxo_value: u128,
+    utxo_token: Option<(H160, u128)>,
+    fund_contract: bool,
+) -> Result<(), &'static str> {
+    // check if `dest` exist and if it does, update its balance information
+    <ContractBalances<T>>::get(&dest).ok_or("Contract doesn't exist!")?;
+    <ContractBalances<T>>::mutate(dest, |info| {
+        info.as_mut().unwrap().utxos.push((utxo_hash, utxo_value));
+    });
+
+    // only if explicitly specified, fund the contract
+    if fund_contract {
+        <ContractBalances<T>>::mutate(dest, |info| {
+            info.as_mut().unwrap().funds += utxo_value.saturated_into::<u128>();
+        });
+    }
+
+    // the tokens of the UTXO are tracked the same way
+    if let Some((token_id, amount)) = utxo_token {
+        <ContractBalances<T>>::mutate(dest, |info| {
+            let token_info = info.as_mut().unwrap().tokens.entry(token_id).or_default();
+            token_info.utxos.push((utxo_hash, amount));
+            if fund_contract {
+                token_info.funds += amount;
+            }
+        });
+    }
+
+    Ok(())
+}
+
+/// Picks the UTXOs of the smart contract needed to send `value`,
+/// in ascending order of outpoints. The UTXO system removes them
+/// from the contract's balance when they are spent, and gives back
+/// to the contract what is left of them as change.
+fn pick_utxos<T: Config>(contract: &T::AccountId, value: u128) -> Result<Vec<H256>, DispatchError> {
+    let fund_info =
+        <ContractBalances<T>>::get(contract).ok_or(DispatchError::Other("Caller doesn't exist"))?;
+    ensure!(fund_info.funds >= value, "Caller doesn't have enough funds");
+
+    Ok(pick_outpoints(fund_info.utxos, value))
+}
+
+/// Picks the UTXOs of the smart contract needed to send `amount` of the token
+/// with `token_id`, the same way as `pick_utxos`.
+fn pick_token_utxos<T: Config>(
+    contract: &T::AccountId,
+    token_id: &H160,
+    amount: u128,
+) -> Result<Vec<H256>, DispatchError> {
+    let fund_info =
+        <ContractBalances<T>>::get(contract).ok_or(DispatchError::Other("Caller doesn't exist"))?;
+    let token_info = fund_info.tokens.get(token_id).cloned().unwrap_or_default();
+    ensure!(
+        token_info.funds >= amount,
+        "Caller doesn't have enough tokens"
+    );
+
+    Ok(pick_outpoints(token_info.utxos, amount))
+}
+
+/// Picks UTXOs in ascending order of outpoints, until they hold `value`.
+fn pick_outpoints(mut utxos: Vec<(H256, u128)>, value: u128) -> Vec<H256> {
+    // consensus-critical sorting, so that all PP validator nodes pick the same UTXOs
+    utxos.sort();
+
+    let mut picked: u128 = 0;
+    let mut outpoints = Vec::new();
+    for (outpoint, utxo_value) in utxos {
+        // at least one UTXO is needed, to have an input to the transaction
+        if picked >= value && !outpoints.is_empty() {
+            break;
+        }
+        picked = picked.saturating_add(utxo_value);
+        outpoints.push(outpoint);
+    }
+
+    outpoints
+}
+
+impl<T: Config> Pallet<T>
+where
+    T::AccountId: UncheckedFrom<T::Hash> + AsRef<[u8]>,
+{
+    /// Returns all the smart contracts created by `CreatePP` UTXOs,
+    /// with the outpoint of the UTXO that created each of them.
+    pub fn contracts() -> Vec<(T::AccountId, H256)> {
+        <ContractCreations<T>>::iter().collect()
+    }
+
+    /// Simulates a `CallPP` UTXO paying `value` to the smart contract `dest`,
+    /// funding included, and the call to the contract with `input_data`.
+    /// Unlike a real call, a reverted call is returned as is.
+    /// The gas limit is capped at the weight of a whole block.
+    /// None of it is committed to storage.
+    pub fn dry_run_call(
+        caller: T::AccountId,
+        dest: T::AccountId,
+        value: u128,
+        fund_contract: bool,
+        input_data: Vec<u8>,
+        gas_limit: Weight,
+    ) -> Result<DryRunResult, DispatchError> {
+        // as in `pallet_contracts_rpc`, no call may take more than a block
+        let gas_limit = gas_limit.min(T::BlockWeights::get().max_block);
+        // the UTXO system rolls back everything, the UTXO paid to `dest` included
+        let (exec_result, transactions) = T::Utxo::dry_run(
+            &dest,
+            value,
+            fund_contract,
+            &input_data,
+            gas_limit,
+            |utxo_hash| -> Result<ContractExecResult, DispatchError> {
+                add_utxo::<T>(&dest, utxo_hash, value, None, fund_contract)?;
+
+                Ok(pallet_contracts::Pallet::<T>::bare_call(
+                    caller,
+                    dest.clone(),
+                    0u32.into(),
+                    gas_limit,
+                    input_data.clone(),
+                    true, // enable debugging
+                ))
+            },
+        );
+        Ok(DryRunResult {
+            exec_result: exec_result?,
+            transactions: transactions.into_iter().map(Bytes).collect(),
+        })
+    }
 }
 
 impl<T: Config> ProgrammablePoolApi for Pallet<T>
@@ -182,38 +485,52 @@ where
         caller: &T::AccountId,
         gas_limit: Weight,
         code: &Vec<u8>,
-        _utxo_hash: H256,
-        _utxo_value: u128,
+        utxo_hash: H256,
+        utxo_value: u128,
         data: &Vec<u8>,
-    ) -> Result<(), &'static str> {
+    ) -> Result<(T::AccountId, Weight), &'static str> {
+        let code_hash = T::Hashing::hash(code);
         let code = pallet_contracts_primitives::Code::Upload(Bytes(code.to_vec()));
         let endowment = pallet_contracts::Pallet::<T>::subsistence_threshold();
 
         let res = pallet_contracts::Pallet::<T>::bare_instantiate(
             caller.clone(),
-            endowment * 100_u32.into(), // TODO
+            endowment,
             gas_limit,
             code,
             data.to_vec(),
             Vec::new(),
             true, // enable debugging
-        )
-        .result
-        .map_err(|e| {
+        );
+        let gas_consumed = res.gas_consumed;
+        let res = res.result.map_err(|e| {
             log::error!("Instantation failed: {:?}", e);
             "Failed to instantiate smart contract"
         })?;
+        ensure!(
+            res.result.is_success(),
+            "Smart contract instantiation reverted"
+        );
 
         // Create balance entry for the smart contract
         <ContractBalances<T>>::insert(
-            res.account_id,
+            &res.account_id,
             Some(ContractBalance {
                 funds: 0,
                 utxos: Vec::new(),
+                tokens: BTreeMap::new(),
             }),
         );
-
-        Ok(())
+        <ContractCreations<T>>::insert(&res.account_id, utxo_hash);
+        // the value of the `CreatePP` utxo funds the contract.
+        add_utxo::<T>(&res.account_id, utxo_hash, utxo_value, None, true)?;
+
+        Pallet::<T>::deposit_event(Event::<T>::ContractInstantiated(
+            res.account_id.clone(),
+            code_hash,
+            utxo_hash,
+        ));
+        Ok((res.account_id, gas_consumed))
     }
 
     fn call(
@@ -222,44 +539,139 @@ where
         gas_limit: Weight,
         utxo_hash: H256,
         utxo_value: u128,
+        utxo_token: Option<(H160, u128)>,
         fund_contract: bool,
         input_data: &Vec<u8>,
-    ) -> Result<(), &'static str> {
-        // check if `dest` exist and if it does, update its balance information
-        <ContractBalances<T>>::get(&dest).ok_or("Contract doesn't exist!")?;
-        <ContractBalances<T>>::mutate(dest, |info| {
-            info.as_mut().unwrap().utxos.push((utxo_hash, utxo_value));
-        });
-
-        // only if explicitly specified, fund the contract
-        if fund_contract {
-            <ContractBalances<T>>::mutate(dest, |info| {
-                info.as_mut().unwrap().funds += utxo_value.saturated_into::<u128>();
-            });
+    ) -> Result<Weight, &'static str> {
+        add_utxo::<T>(dest, utxo_hash, utxo_value, utxo_token, fund_contract)?;
+
+        // without input data, the contract is only funded
+        if input_data.is_empty() {
+            Pallet::<T>::deposit_event(Event::<T>::ContractCalled(
+                caller.clone(),
+                dest.clone(),
+                utxo_hash,
+                utxo_value,
+            ));
+            return Ok(0);
         }
 
-        let _ = pallet_contracts::Pallet::<T>::bare_call(
+        let res = pallet_contracts::Pallet::<T>::bare_call(
             caller.clone(),
             dest.clone(),
             0u32.into(),
             gas_limit,
             input_data.to_vec(),
             true, // enable debugging
-        )
-        .result
-        .map_err(|e| {
+        );
+        let gas_consumed = res.gas_consumed;
+        let res = res.result.map_err(|e| {
             log::error!("Call failed: {:?}", e);
             "Failed to call smart contract"
         })?;
+        if !res.is_success() {
+            log::error!("Call to {:?} reverted: {:?}", dest, res.data);
+            return Err("Smart contract call reverted");
+        }
 
-        Ok(())
+        // nothing could spend the UTXOs of a contract after it is gone,
+        // so it has to sweep them by terminating through the `Terminate` chain extension call
+        if let Some(contract) = terminated_without_sweeping::<T>() {
+            log::error!("{:?} terminated without sweeping its UTXOs", contract);
+            return Err("Smart contract terminated without sweeping its UTXOs");
+        }
+
+        Pallet::<T>::deposit_event(Event::<T>::ContractCalled(
+            caller.clone(),
+            dest.clone(),
+            utxo_hash,
+            utxo_value,
+        ));
+        Ok(gas_consumed)
     }
+
+    fn on_utxo_spent(
+        contract: &T::AccountId,
+        utxo_hash: H256,
+        utxo_value: u128,
+        utxo_token: Option<(H160, u128)>,
+        fund_contract: bool,
+    ) -> Result<(), &'static str> {
+        <ContractBalances<T>>::try_mutate(contract, |info| -> Result<(), &'static str> {
+            let info = info.as_mut().ok_or("Contract doesn't exist!")?;
+            let index = info
+                .utxos
+                .iter()
+                .position(|(hash, _)| *hash == utxo_hash)
+                .ok_or("Contract doesn't own the UTXO")?;
+            info.utxos.remove(index);
+
+            if fund_contract {
+                info.funds =
+                    info.funds.checked_sub(utxo_value).ok_or("Contract funds underflow")?;
+            }
+
+            if let Some((token_id, amount)) = utxo_token {
+                let token_info =
+                    info.tokens.get_mut(&token_id).ok_or("Contract doesn't own the token")?;
+                token_info.utxos.retain(|(hash, _)| *hash != utxo_hash);
+                if fund_contract {
+                    token_info.funds = token_info
+                        .funds
+                        .checked_sub(amount)
+                        .ok_or("Contract token funds underflow")?;
+                }
+                if token_info.utxos.is_empty() {
+                    info.tokens.remove(&token_id);
+                }
+            }
+            Ok(())
+        })
+    }
+
+    fn contract_balance(contract: &T::AccountId) -> Option<(u128, Vec<(H256, u128)>)> {
+        <ContractBalances<T>>::get(contract).map(|info| (info.funds, info.utxos))
+    }
+
+    fn contract_token_balance(
+        contract: &T::AccountId,
+        token_id: &H160,
+    ) -> Option<(u128, Vec<(H256, u128)>)> {
+        let info = <ContractBalances<T>>::get(contract)?;
+        info.tokens
+            .get(token_id)
+            .map(|token_info| (token_info.funds, token_info.utxos.clone()))
+    }
+}
+
+/// Weight of verifying an sr25519 signature, without hashing the message
+const SR25519_VERIFY_WEIGHT: Weight = 50 * WEIGHT_PER_MICROS;
+
+/// Weight of hashing one byte of the message of an sr25519 signature
+const SR25519_VERIFY_PER_BYTE_WEIGHT: Weight = 5 * WEIGHT_PER_NANOS;
+
+/// Weight of reading one byte of an output or of token data and returning it to the smart contract
+const READ_PER_BYTE_WEIGHT: Weight = WEIGHT_PER_NANOS;
+
+/// The smart contract that is running the chain extension. Only its own
+/// UTXOs may be spent, so it is never taken from the input of the contract.
+fn contract_address<T: Config, E: Ext>(ext: &E) -> Result<T::AccountId, DispatchError> {
+    T::AccountId::decode(&mut ext.address().encode().as_slice())
+        .map_err(|_| DispatchError::Other("Failed to get contract's account"))
 }
 
 enum ChainExtensionCall {
     Transfer = 1000,
     Balance = 1001,
     Call = 1002,
+    Utxos = 1003,
+    Utxo = 1004,
+    TokenInfo = 1005,
+    BlockTime = 1006,
+    Sr25519Verify = 1007,
+    TransferToken = 1008,
+    TokenBalance = 1009,
+    Terminate = 1010,
 }
 
 impl<T: pallet_contracts::Config + pallet::Config> ChainExtension<T> for Pallet<T> {
@@ -270,15 +682,15 @@ impl<T: pallet_contracts::Config + pallet::Config> ChainExtension<T> for Pallet<
         match func_id {
             x if x == ChainExtensionCall::Transfer as u32 => {
                 let mut env = env.buf_in_buf_out();
-                let (acc_id, dest, value): (T::AccountId, T::AccountId, u128) = env.read_as()?;
-
-                if !<ContractBalances<T>>::get(&dest).is_none() {
-                    return Err(DispatchError::Other(
-                        "Contract-to-contract transactions not implemented",
-                    ));
-                }
-
-                send_p2pk_tx::<T>(&acc_id, &dest, value)?
+                let (dest, value): (T::AccountId, u128) = env.read_as()?;
+                let acc_id = contract_address::<T, E>(env.ext())?;
+
+                let spend_weight = if <ContractBalances<T>>::get(&dest).is_some() {
+                    send_c2c_transfer::<T>(&acc_id, &dest, value)?
+                } else {
+                    send_p2pk_tx::<T>(&acc_id, &dest, value)?
+                };
+                env.charge_weight(spend_weight)?;
             }
             x if x == ChainExtensionCall::Balance as u32 => {
                 let mut env = env.buf_in_buf_out();
@@ -295,12 +707,9 @@ impl<T: pallet_contracts::Config + pallet::Config> ChainExtension<T> for Pallet<
                 // `read_as_unbounded()` has to be used here because the size of `data`
                 //  is only known during runtime
                 let mut env = env.buf_in_buf_out();
-                let (acc_id, dest, selector, mut data): (
-                    T::AccountId,
-                    T::AccountId,
-                    [u8; 4],
-                    Vec<u8>,
-                ) = env.read_as_unbounded(env.in_len())?;
+                let (dest, value, selector, mut data): (T::AccountId, u128, [u8; 4], Vec<u8>) =
+                    env.read_as_unbounded(env.in_len())?;
+                let acc_id = contract_address::<T, E>(env.ext())?;
 
                 if <ContractBalances<T>>::get(&dest).is_none() {
                     return Err(DispatchError::Other("Destination doesn't exist"));
@@ -311,8 +720,135 @@ impl<T: pallet_contracts::Config + pallet::Config> ChainExtension<T> for Pallet<
                 let mut selector = selector.to_vec();
                 selector.append(&mut data);
 
-                // C2C transfers all funds as refunding to a contract is not possible (at least for now)
-                send_c2c_tx::<T>(&acc_id, &dest, &selector)?
+                // the called contract may use all the gas left to the calling contract,
+                // which then pays for what was consumed.
+                let gas_limit = env.ext().gas_meter().gas_left();
+
+                // C2C transfers `value` from the calling contract, which gets back the change,
+                // and pays for the gas consumed by `dest` and for the storage of the transaction
+                let weight = send_c2c_tx::<T>(&acc_id, &dest, value, &selector, gas_limit)?;
+                env.charge_weight(weight)?;
+            }
+            x if x == ChainExtensionCall::Utxos as u32 => {
+                let mut env = env.buf_in_buf_out();
+                let acc_id: T::AccountId = env.read_as()?;
+                env.charge_weight(T::DbWeight::get().reads(1))?;
+
+                let fund_info = <ContractBalances<T>>::get(&acc_id).ok_or(DispatchError::Other(
+                    "Contract doesn't own any UTXO or it doesn't exist!",
+                ))?;
+                let utxos = fund_info.utxos.encode();
+                env.charge_weight(READ_PER_BYTE_WEIGHT.saturating_mul(utxos.len() as Weight))?;
+
+                env.write(&utxos, false, None)
+                    .map_err(|_| DispatchError::Other("Failed to return value?"))?;
+            }
+            x if x == ChainExtensionCall::Utxo as u32 => {
+                let mut env = env.buf_in_buf_out();
+                let outpoint: H256 = env.read_as()?;
+
+                // SCALE-encoded output, or `None` if the outpoint is spent or doesn't exist
+                let utxo = T::Utxo::utxo(&outpoint).encode();
+                env.charge_weight(
+                    T::DbWeight::get()
+                        .reads(1)
+                        .saturating_add(READ_PER_BYTE_WEIGHT.saturating_mul(utxo.len() as Weight)),
+                )?;
+
+                env.write(&utxo, false, None)
+                    .map_err(|_| DispatchError::Other("Failed to return value?"))?;
+            }
+            x if x == ChainExtensionCall::TokenInfo as u32 => {
+                let mut env = env.buf_in_buf_out();
+                let token_id: H160 = env.read_as()?;
+                env.charge_weight(T::DbWeight::get().reads(1))?;
+
+                // SCALE-encoded `TokenIssuanceV1` data, or `None` if the token doesn't exist
+                let token_info = T::Utxo::token_issuance(&token_id).encode();
+                env.charge_weight(READ_PER_BYTE_WEIGHT.saturating_mul(token_info.len() as Weight))?;
+
+                env.write(&token_info, false, None)
+                    .map_err(|_| DispatchError::Other("Failed to return value?"))?;
+            }
+            x if x == ChainExtensionCall::BlockTime as u32 => {
+                let mut env = env.buf_in_buf_out();
+                env.charge_weight(T::DbWeight::get().reads(2))?;
+
+                // the block number and timestamp (in seconds) that time locks are checked against
+                let block_number =
+                    <frame_system::Pallet<T>>::block_number().saturated_into::<u64>();
+                let timestamp = T::Utxo::timestamp();
+
+                env.write(&(block_number, timestamp).encode(), false, None)
+                    .map_err(|_| DispatchError::Other("Failed to return value?"))?;
+            }
+            x if x == ChainExtensionCall::Sr25519Verify as u32 => {
+                let mut env = env.buf_in_buf_out();
+                // charged on the whole input, before reading it, as the message is unbounded
+                env.charge_weight(SR25519_VERIFY_WEIGHT.saturating_add(
+                    SR25519_VERIFY_PER_BYTE_WEIGHT.saturating_mul(env.in_len() as Weight),
+                ))?;
+                let (signature, public, message): (H512, H256, Vec<u8>) =
+                    env.read_as_unbounded(env.in_len())?;
+
+                let valid = crypto::sr25519_verify(
+                    &sr25519::Signature::from_raw(signature.into()),
+                    &message,
+                    &sr25519::Public::from_raw(public.into()),
+                );
+
+                env.write(&valid.encode(), false, None)
+                    .map_err(|_| DispatchError::Other("Failed to return value?"))?;
+            }
+            x if x == ChainExtensionCall::TransferToken as u32 => {
+                let mut env = env.buf_in_buf_out();
+                let (dest, token_id, amount): (T::AccountId, H160, u128) = env.read_as()?;
+                let acc_id = contract_address::<T, E>(env.ext())?;
+
+                let spend_weight = send_token_tx::<T>(&acc_id, &dest, &token_id, amount)?;
+                env.charge_weight(spend_weight)?;
+            }
+            x if x == ChainExtensionCall::TokenBalance as u32 => {
+                let mut env = env.buf_in_buf_out();
+                let (acc_id, token_id): (T::AccountId, H160) = env.read_as()?;
+                env.charge_weight(T::DbWeight::get().reads(1))?;
+
+                let fund_info = <ContractBalances<T>>::get(&acc_id).ok_or(DispatchError::Other(
+                    "Contract doesn't own any UTXO or it doesn't exist!",
+                ))?;
+                let funds =
+                    fund_info.tokens.get(&token_id).map_or(0, |token_info| token_info.funds);
+
+                env.write(&funds.encode(), false, None)
+                    .map_err(|_| DispatchError::Other("Failed to return value?"))?;
+            }
+            x if x == ChainExtensionCall::Terminate as u32 => {
+                let mut env = env.buf_in_buf_out();
+                let (acc_id, beneficiary): (T::AccountId, Beneficiary) = env.read_as()?;
+                env.charge_weight(
+                    <T as pallet_contracts::Config>::Schedule::get().host_fn_weights.terminate,
+                )?;
+                ensure!(
+                    env.ext().address().encode() == acc_id.encode(),
+                    "Only a smart contract can terminate itself"
+                );
+
+                sweep_utxos::<T>(&acc_id, &beneficiary)?;
+
+                // the native balance of the contract goes to the beneficiary if it is
+                // a public key, and to the caller of the contract otherwise
+                let native_beneficiary = match beneficiary {
+                    Beneficiary::Pubkey(pubkey) => Decode::decode(&mut pubkey.as_bytes())
+                        .map_err(|_| DispatchError::Other("Failed to get beneficiary's account"))?,
+                    Beneficiary::ScriptHash(_) => env.ext().caller().clone(),
+                };
+                env.ext().terminate(&native_beneficiary)?;
+
+                // as with `seal_terminate`, the contract doesn't run after terminating
+                return Ok(RetVal::Diverging {
+                    flags: ReturnFlags::empty(),
+                    data: Vec::new(),
+                });
             }
             _ => {
                 log::error!("Called an unregistered `func_id`: {:}", func_id);
@@ -14,122 +14,341 @@
 // limitations under the License.
 //
 // Author(s): A. Altonen
-// use crate as pallet_pp;
-// use sp_core::H256;
-// use frame_support::parameter_types;
-// use sp_runtime::{
-// 	traits::{BlakeTwo256, IdentityLookup}, testing::Header,
-// };
-// use frame_system as system;
-
-// type UncheckedExtrinsic = frame_system::mocking::MockUncheckedExtrinsic<Test>;
-// type Block = frame_system::mocking::MockBlock<Test>;
-
-// // Configure a mock runtime to test the pallet.
-// frame_support::construct_runtime!(
-// 	pub enum Test where
-// 		Block = Block,
-// 		NodeBlock = Block,
-// 		UncheckedExtrinsic = UncheckedExtrinsic,
-// 	{
-// 		System: frame_system::{Pallet, Call, Config, Storage, Event<T>},
-// 		PpModule: pallet_pp::{Pallet, Call, Storage, Event<T>},
-// 	}
-// );
-
-// parameter_types! {
-// 	pub const BlockHashCount: u64 = 250;
-// 	pub const SS58Prefix: u8 = 42;
-// }
-
-// impl system::Config for Test {
-// 	type BaseCallFilter = frame_support::traits::AllowAll;
-// 	type BlockWeights = ();
-// 	type BlockLength = ();
-// 	type DbWeight = ();
-// 	type Origin = Origin;
-// 	type Call = Call;
-// 	type Index = u64;
-// 	type BlockNumber = u64;
-// 	type Hash = H256;
-// 	type Hashing = BlakeTwo256;
-// 	type AccountId = u64;
-// 	type Lookup = IdentityLookup<Self::AccountId>;
-// 	type Header = Header;
-// 	type Event = Event;
-// 	type BlockHashCount = BlockHashCount;
-// 	type Version = ();
-// 	type PalletInfo = PalletInfo;
-// 	type AccountData = ();
-// 	type OnNewAccount = ();
-// 	type OnKilledAccount = ();
-// 	type SystemWeightInfo = ();
-// 	type SS58Prefix = SS58Prefix;
-// 	type OnSetCode = ();
-// }
-
-// impl pallet_pp::Config for Test {
-// 	type Event = Event;
-// }
-
-// parameter_types! {
-//     pub TombstoneDeposit: Balance = deposit(
-//         1,
-//         <pallet_contracts::Pallet<Runtime>>::contract_info_size()
-//     );
-//     pub DepositPerContract: Balance = TombstoneDeposit::get();
-//     pub const DepositPerStorageByte: Balance = deposit(0, 1);
-//     pub const DepositPerStorageItem: Balance = deposit(1, 0);
-//     pub RentFraction: Perbill = Perbill::from_rational(1u32, 30 * DAYS);
-//     pub const SurchargeReward: Balance = 150 * MILLICENTS;
-//     pub const SignedClaimHandicap: u32 = 2;
-//     pub const MaxValueSize: u32 = 16 * 1024;
-
-//     // The lazy deletion runs inside on_initialize.
-//     pub DeletionWeightLimit: Weight = AVERAGE_ON_INITIALIZE_RATIO *
-//     BlockWeights::get().max_block;
-
-//     // The weight needed for decoding the queue should be less or equal than a fifth
-//     // of the overall weight dedicated to the lazy deletion.
-//     pub DeletionQueueDepth: u32 = ((DeletionWeightLimit::get() / (
-//         <Runtime as pallet_contracts::Config>::WeightInfo::on_initialize_per_queue_item(1) -
-//         <Runtime as pallet_contracts::Config>::WeightInfo::on_initialize_per_queue_item(0)
-//     )) / 5) as u32;
-
-//     pub Schedule: pallet_contracts::Schedule<Runtime> = Default::default();
-// }
-
-// impl pallet_contracts::Config for Runtime {
-//     type Time = Timestamp;
-//     type Randomness = RandomnessCollectiveFlip;
-//     type Currency = Balances;
-//     type Event = Event;
-//     type RentPayment = ();
-//     type SignedClaimHandicap = SignedClaimHandicap;
-//     type TombstoneDeposit = TombstoneDeposit;
-//     type DepositPerContract = DepositPerContract;
-//     type DepositPerStorageByte = DepositPerStorageByte;
-//     type DepositPerStorageItem = DepositPerStorageItem;
-//     type RentFraction = RentFraction;
-//     type SurchargeReward = SurchargeReward;
-//     type WeightPrice = pallet_transaction_payment::Pallet<Self>;
-//     type WeightInfo = pallet_contracts::weights::SubstrateWeight<Self>;
-//     type ChainExtension = ();
-//     type DeletionQueueDepth = DeletionQueueDepth;
-//     type DeletionWeightLimit = DeletionWeightLimit;
-//     type Call = Call;
-//     /// The safest default is to allow no calls at all.
-//     ///
-//     /// Runtimes should whitelist dispatchables that are allowed to be called from contracts
-//     /// and make sure they are stable. Dispatchables exposed to contracts are not allowed to
-//     /// change because that would break already deployed contracts. The `Call` structure itself
-//     /// is not allowed to change the indices of existing pallets, too.
-//     type CallFilter = DenyAll;
-//     type Schedule = Schedule;
-//     type CallStack = [pallet_contracts::Frame<Self>; 31];
-// }
-
-// // Build genesis storage according to the mock runtime.
-// pub fn new_test_ext() -> sp_io::TestExternalities {
-// 	system::GenesisConfig::default().build_storage::<Test>().unwrap().into()
-// }
+use crate as pallet_pp;
+use crate::{ContractBalance, ContractBalances, ContractCreations};
+use codec::Encode;
+use frame_support::{
+    dispatch::{DispatchResultWithPostInfo, Vec},
+    ensure, parameter_types,
+    traits::Nothing,
+    weights::{constants::RocksDbWeight, Weight},
+};
+use pp_api::ProgrammablePoolApi;
+use sp_core::{
+    sp_std::{cell::RefCell, collections::btree_map::BTreeMap},
+    H160, H256, H512,
+};
+use sp_runtime::{
+    testing::Header,
+    traits::{BlakeTwo256, Convert, Hash, IdentityLookup},
+    AccountId32, DispatchError,
+};
+use utxo_api::{Beneficiary, UtxoApi};
+
+pub type UncheckedExtrinsic = frame_system::mocking::MockUncheckedExtrinsic<Test>;
+pub type Block = frame_system::mocking::MockBlock<Test>;
+pub type AccountId = AccountId32;
+
+thread_local! {
+    // the owner of each unspent utxo, its value, and whether it funds the owner if it is a contract.
+    pub static MOCK_UTXOS: RefCell<BTreeMap<H256, (AccountId, u128, bool)>> =
+        RefCell::new(BTreeMap::new());
+    pub static NEXT_OUTPOINT: RefCell<u64> = RefCell::new(0);
+}
+
+// Configure a mock runtime to test the pallet.
+frame_support::construct_runtime!(
+    pub enum Test where
+        Block = Block,
+        NodeBlock = Block,
+        UncheckedExtrinsic = UncheckedExtrinsic,
+    {
+        System: frame_system::{Pallet, Call, Config, Storage, Event<T>},
+        Balances: pallet_balances::{Pallet, Call, Storage, Config<T>, Event<T>},
+        Timestamp: pallet_timestamp::{Pallet, Call, Storage, Inherent},
+        RandomnessCollectiveFlip: pallet_randomness_collective_flip::{Pallet, Storage},
+        Contracts: pallet_contracts::{Pallet, Call, Storage, Event<T>},
+        Pp: pallet_pp::{Pallet, Call, Config<T>, Storage, Event<T>},
+    }
+);
+
+parameter_types! {
+    pub const BlockHashCount: u64 = 250;
+    pub const SS58Prefix: u8 = 42;
+    pub const ExistentialDeposit: u64 = 1;
+    pub const MinimumPeriod: u64 = 1;
+    pub const DeletionQueueDepth: u32 = 1024;
+    pub const DeletionWeightLimit: Weight = 500_000_000_000;
+    pub Schedule: pallet_contracts::Schedule<Test> = Default::default();
+}
+
+impl frame_system::Config for Test {
+    type BaseCallFilter = frame_support::traits::Everything;
+    type BlockWeights = ();
+    type BlockLength = ();
+    type Origin = Origin;
+    type Call = Call;
+    type Index = u64;
+    type BlockNumber = u64;
+    type Hash = H256;
+    type Hashing = BlakeTwo256;
+    type AccountId = AccountId;
+    type Lookup = IdentityLookup<Self::AccountId>;
+    type Header = Header;
+    type Event = Event;
+    type BlockHashCount = BlockHashCount;
+    type DbWeight = RocksDbWeight;
+    type Version = ();
+    type PalletInfo = PalletInfo;
+    type AccountData = pallet_balances::AccountData<u64>;
+    type OnNewAccount = ();
+    type OnKilledAccount = ();
+    type SystemWeightInfo = ();
+    type SS58Prefix = SS58Prefix;
+    type OnSetCode = ();
+}
+
+impl pallet_balances::Config for Test {
+    type MaxLocks = ();
+    type Balance = u64;
+    type Event = Event;
+    type DustRemoval = ();
+    type ExistentialDeposit = ExistentialDeposit;
+    type AccountStore = System;
+    type WeightInfo = ();
+    type MaxReserves = ();
+    type ReserveIdentifier = [u8; 8];
+}
+
+impl pallet_timestamp::Config for Test {
+    type Moment = u64;
+    type OnTimestampSet = ();
+    type MinimumPeriod = MinimumPeriod;
+    type WeightInfo = ();
+}
+
+impl pallet_randomness_collective_flip::Config for Test {}
+
+impl Convert<Weight, u64> for Test {
+    fn convert(weight: Weight) -> u64 {
+        weight
+    }
+}
+
+impl pallet_contracts::Config for Test {
+    type Time = Timestamp;
+    type Randomness = RandomnessCollectiveFlip;
+    type Currency = Balances;
+    type Event = Event;
+    type WeightPrice = Self;
+    type WeightInfo = ();
+    type ChainExtension = Pp;
+    type DeletionQueueDepth = DeletionQueueDepth;
+    type DeletionWeightLimit = DeletionWeightLimit;
+    type Call = Call;
+    type CallFilter = Nothing;
+    type Schedule = Schedule;
+    type CallStack = [pallet_contracts::Frame<Self>; 31];
+    type ContractDeposit = ();
+}
+
+impl pallet_pp::Config for Test {
+    type Event = Event;
+    type Utxo = MockUtxo;
+}
+
+/// A UTXO system that only keeps the owner and the value of each utxo.
+/// Like `pallet-utxo`, it tells `pallet-pp` about the utxos of contracts that are spent
+/// and created, and gives back the change to the spending contract.
+pub struct MockUtxo;
+
+impl MockUtxo {
+    fn new_utxo(owner: &AccountId, value: u128, fund: bool) -> H256 {
+        let outpoint = NEXT_OUTPOINT.with(|next| {
+            let mut next = next.borrow_mut();
+            *next += 1;
+            BlakeTwo256::hash_of(&*next)
+        });
+        MOCK_UTXOS.with(|utxos| utxos.borrow_mut().insert(outpoint, (owner.clone(), value, fund)));
+        outpoint
+    }
+
+    // only the owner of the utxos can spend them. Returns their total value.
+    fn spend_utxos(caller: &AccountId, outpoints: &Vec<H256>) -> Result<u128, DispatchError> {
+        let mut total: u128 = 0;
+        for outpoint in outpoints {
+            let (owner, value, fund) = MOCK_UTXOS
+                .with(|utxos| utxos.borrow_mut().remove(outpoint))
+                .ok_or("UTXO doesn't exist!")?;
+            ensure!(owner == *caller, "only the contract can spend its utxos");
+            Pp::on_utxo_spent(caller, *outpoint, value, None, fund)?;
+            total = total.checked_add(value).ok_or("input value overflow")?;
+        }
+        Ok(total)
+    }
+
+    fn pay_to_contract(
+        caller: &AccountId,
+        contract: &AccountId,
+        value: u128,
+        data: &Vec<u8>,
+        gas_limit: Weight,
+    ) -> Result<(Weight, H256), DispatchError> {
+        let outpoint = Self::new_utxo(contract, value, true);
+        let gas_consumed = Pp::call(
+            caller, contract, gas_limit, outpoint, value, None, true, data,
+        )?;
+        Ok((gas_consumed, outpoint))
+    }
+
+    fn send(
+        caller: &AccountId,
+        value: u128,
+        outpoints: &Vec<H256>,
+        pay: impl FnOnce() -> Result<(Weight, H256), DispatchError>,
+    ) -> Result<(Weight, Vec<H256>), DispatchError> {
+        let total = Self::spend_utxos(caller, outpoints)?;
+        let change = total.checked_sub(value).ok_or("Contract doesn't have enough funds")?;
+
+        let (gas_consumed, outpoint) = pay()?;
+        let mut new_utxos = vec![outpoint];
+        if change > 0 {
+            let (_, change_outpoint) = Self::pay_to_contract(caller, caller, change, &vec![], 0)?;
+            new_utxos.push(change_outpoint);
+        }
+        Ok((gas_consumed, new_utxos))
+    }
+}
+
+impl UtxoApi for MockUtxo {
+    type AccountId = AccountId;
+
+    fn spend(
+        _caller: &Self::AccountId,
+        _value: u128,
+        _address: H256,
+        _utxo: H256,
+        _sig: H512,
+    ) -> DispatchResultWithPostInfo {
+        Ok(().into())
+    }
+
+    fn unlock_request_for_withdrawal(
+        _stash_account_caller: &Self::AccountId,
+    ) -> DispatchResultWithPostInfo {
+        Ok(().into())
+    }
+
+    fn withdraw_stake(_stash_account_caller: &Self::AccountId) -> DispatchResultWithPostInfo {
+        Ok(().into())
+    }
+
+    fn send_conscrit_p2pk(
+        caller: &Self::AccountId,
+        destination: &Self::AccountId,
+        value: u128,
+        outpoints: &Vec<H256>,
+    ) -> Result<Vec<H256>, DispatchError> {
+        let (_, new_utxos) = Self::send(caller, value, outpoints, || {
+            Ok((0, Self::new_utxo(destination, value, false)))
+        })?;
+        Ok(new_utxos)
+    }
+
+    fn send_conscrit_c2c(
+        caller: &Self::AccountId,
+        destination: &Self::AccountId,
+        value: u128,
+        data: &Vec<u8>,
+        outpoints: &Vec<H256>,
+        gas_limit: Weight,
+    ) -> Result<(Weight, Vec<H256>), DispatchError> {
+        Self::send(caller, value, outpoints, || {
+            Self::pay_to_contract(caller, destination, value, data, gas_limit)
+        })
+    }
+
+    fn send_conscrit_token_p2pk(
+        _caller: &Self::AccountId,
+        _destination: &Self::AccountId,
+        _token_id: &H160,
+        _amount: u128,
+        _outpoints: &Vec<H256>,
+    ) -> Result<Vec<H256>, DispatchError> {
+        Err(DispatchError::Other("tokens are not supported by the mock"))
+    }
+
+    fn send_conscrit_token_c2c(
+        _caller: &Self::AccountId,
+        _destination: &Self::AccountId,
+        _token_id: &H160,
+        _amount: u128,
+        _outpoints: &Vec<H256>,
+    ) -> Result<Vec<H256>, DispatchError> {
+        Err(DispatchError::Other("tokens are not supported by the mock"))
+    }
+
+    fn send_conscrit_sweep(
+        caller: &Self::AccountId,
+        beneficiary: &Beneficiary,
+        outpoints: &Vec<H256>,
+    ) -> Result<Vec<H256>, DispatchError> {
+        let total = Self::spend_utxos(caller, outpoints)?;
+        let beneficiary = match beneficiary {
+            Beneficiary::Pubkey(hash) | Beneficiary::ScriptHash(hash) => AccountId::from(hash.0),
+        };
+        Ok(vec![Self::new_utxo(&beneficiary, total, false)])
+    }
+
+    fn utxo(outpoint: &H256) -> Option<Vec<u8>> {
+        MOCK_UTXOS.with(|utxos| utxos.borrow().get(outpoint).map(Encode::encode))
+    }
+
+    fn token_issuance(_token_id: &H160) -> Option<Vec<u8>> {
+        None
+    }
+
+    fn timestamp() -> u64 {
+        0
+    }
+
+    fn dry_run<R>(
+        destination: &Self::AccountId,
+        value: u128,
+        fund_contract: bool,
+        _data: &Vec<u8>,
+        _gas_limit: Weight,
+        call: impl FnOnce(H256) -> R,
+    ) -> (R, Vec<Vec<u8>>) {
+        let utxo_hash = Self::new_utxo(destination, value, fund_contract);
+        (call(utxo_hash), Vec::new())
+    }
+}
+
+/// Registers `contract` with an empty balance, as `create` does after the instantiation.
+pub fn new_contract(contract: &AccountId) {
+    <ContractBalances<Test>>::insert(
+        contract,
+        Some(ContractBalance {
+            funds: 0,
+            utxos: Vec::new(),
+            tokens: BTreeMap::new(),
+        }),
+    );
+    <ContractCreations<Test>>::insert(contract, H256::zero());
+}
+
+/// Pays a utxo of `value` to `contract`, without calling it.
+/// Returns the outpoint of the utxo.
+pub fn fund_contract(contract: &AccountId, value: u128, fund: bool) -> H256 {
+    let outpoint = MockUtxo::new_utxo(contract, value, fund);
+    assert_eq!(
+        Pp::call(contract, contract, 0, outpoint, value, None, fund, &vec![]),
+        Ok(0)
+    );
+    outpoint
+}
+
+/// The owner and the value of the utxo at `outpoint`, if it is unspent.
+pub fn mock_utxo(outpoint: &H256) -> Option<(AccountId, u128)> {
+    MOCK_UTXOS
+        .with(|utxos| utxos.borrow().get(outpoint).map(|(owner, value, _)| (owner.clone(), *value)))
+}
+
+// Build genesis storage according to the mock runtime.
+pub fn new_test_ext() -> sp_io::TestExternalities {
+    let t = frame_system::GenesisConfig::default().build_storage::<Test>().unwrap();
+    let mut ext = sp_io::TestExternalities::new(t);
+    ext.execute_with(|| System::set_block_number(1));
+    ext
+}
@@ -14,26 +14,239 @@
 // limitations under the License.
 //
 // Author(s): A. Altonen
-// use crate::{Error, mock::*};
-// use frame_support::{assert_ok, assert_noop};
-
-// #[test]
-// fn it_works_for_default_value() {
-// 	new_test_ext().execute_with(|| {
-// 		// Dispatch a signed extrinsic.
-// 		assert_ok!(PpModule::do_something(Origin::signed(1), 42));
-// 		// Read pallet storage and assert an expected result.
-// 		assert_eq!(PpModule::something(), Some(42));
-// 	});
-// }
-
-// #[test]
-// fn correct_error_for_none_value() {
-// 	new_test_ext().execute_with(|| {
-// 		// Ensure the expected error is thrown when no value is present.
-// 		assert_noop!(
-// 			PpModule::cause_error(Origin::signed(1)),
-// 			Error::<Test>::NoneValue
-// 		);
-// 	});
-// }
+use crate::{
+    mock::*, pick_outpoints, send_c2c_transfer, send_c2c_tx, send_p2pk_tx, sweep_utxos,
+    terminated_without_sweeping,
+};
+use frame_support::{assert_err, assert_ok, traits::Get, weights::constants::RocksDbWeight};
+use pp_api::ProgrammablePoolApi;
+use sp_core::H256;
+use utxo_api::Beneficiary;
+
+fn alice() -> AccountId {
+    AccountId::from([1u8; 32])
+}
+
+fn contract_a() -> AccountId {
+    AccountId::from([10u8; 32])
+}
+
+fn contract_b() -> AccountId {
+    AccountId::from([11u8; 32])
+}
+
+#[test]
+fn picks_outpoints_in_ascending_order() {
+    let utxos =
+        vec![(H256::repeat_byte(3), 5), (H256::repeat_byte(1), 10), (H256::repeat_byte(2), 7)];
+
+    assert_eq!(
+        pick_outpoints(utxos.clone(), 12),
+        vec![H256::repeat_byte(1), H256::repeat_byte(2)]
+    );
+    assert_eq!(
+        pick_outpoints(utxos.clone(), 10),
+        vec![H256::repeat_byte(1)]
+    );
+    // at least one utxo is picked, to have an input to the transaction.
+    assert_eq!(pick_outpoints(utxos.clone(), 0), vec![H256::repeat_byte(1)]);
+    // there is no check of the value here, the funds of the contract are checked instead.
+    assert_eq!(pick_outpoints(utxos, 100).len(), 3);
+}
+
+#[test]
+fn transfer_gives_back_the_change() {
+    new_test_ext().execute_with(|| {
+        new_contract(&contract_a());
+        let utxo1 = fund_contract(&contract_a(), 10, true);
+        let utxo2 = fund_contract(&contract_a(), 7, true);
+        assert_eq!(Pp::contract_balance(&contract_a()).unwrap().0, 17);
+
+        // more than what is needed may be picked, but not more than the contract's funds.
+        assert_err!(
+            send_p2pk_tx::<Test>(&contract_a(), &alice(), 18),
+            "Caller doesn't have enough funds"
+        );
+        // the storage of the two inputs and the two outputs is paid for.
+        assert_eq!(
+            send_p2pk_tx::<Test>(&contract_a(), &alice(), 12),
+            Ok(RocksDbWeight::get().reads_writes(8, 8))
+        );
+
+        // both utxos were spent, and 5 came back to the contract as change.
+        assert_eq!(mock_utxo(&utxo1), None);
+        assert_eq!(mock_utxo(&utxo2), None);
+        let (funds, utxos) = Pp::contract_balance(&contract_a()).unwrap();
+        assert_eq!(funds, 5);
+        assert_eq!(utxos.len(), 1);
+        assert_eq!(mock_utxo(&utxos[0].0), Some((contract_a(), 5)));
+        assert_eq!(
+            MOCK_UTXOS.with(|utxos| utxos
+                .borrow()
+                .values()
+                .filter(|(owner, value, _)| *owner == alice() && *value == 12)
+                .count()),
+            1
+        );
+    })
+}
+
+#[test]
+fn c2c_transfer_funds_the_other_contract() {
+    new_test_ext().execute_with(|| {
+        new_contract(&contract_a());
+        new_contract(&contract_b());
+        fund_contract(&contract_a(), 10, true);
+
+        assert_ok!(send_c2c_transfer::<Test>(&contract_a(), &contract_b(), 4));
+        assert_eq!(Pp::contract_balance(&contract_a()).unwrap().0, 6);
+        let (funds, utxos) = Pp::contract_balance(&contract_b()).unwrap();
+        assert_eq!(funds, 4);
+        assert_eq!(mock_utxo(&utxos[0].0), Some((contract_b(), 4)));
+        assert!(System::events().iter().any(|record| matches!(
+            &record.event,
+            Event::Pp(crate::Event::ContractPaidOut(contract, new_utxos))
+                if *contract == contract_a() && new_utxos.len() == 2
+        )));
+
+        // a contract only ever spends its own utxos.
+        assert_err!(
+            send_c2c_transfer::<Test>(&contract_b(), &contract_a(), 5),
+            "Caller doesn't have enough funds"
+        );
+        assert_eq!(Pp::contract_balance(&contract_a()).unwrap().0, 6);
+    })
+}
+
+#[test]
+fn c2c_call_is_limited_to_its_value() {
+    new_test_ext().execute_with(|| {
+        new_contract(&contract_a());
+        new_contract(&contract_b());
+        fund_contract(&contract_a(), 10, true);
+
+        assert_err!(
+            send_c2c_tx::<Test>(&contract_a(), &contract_b(), 11, &vec![1, 2, 3, 4], 100),
+            "Caller doesn't have enough funds"
+        );
+        assert_eq!(Pp::contract_balance(&contract_a()).unwrap().0, 10);
+    })
+}
+
+#[test]
+fn contract_cannot_spend_other_contracts_utxos() {
+    new_test_ext().execute_with(|| {
+        new_contract(&contract_a());
+        new_contract(&contract_b());
+        fund_contract(&contract_a(), 1, true);
+        let utxo_b = fund_contract(&contract_b(), 10, true);
+
+        // the chain extension spends as the running contract, here contract A, which can't
+        // reach the funds of contract B whatever it asks for.
+        assert_err!(
+            send_p2pk_tx::<Test>(&contract_a(), &alice(), 10),
+            "Caller doesn't have enough funds"
+        );
+        assert_err!(
+            send_c2c_transfer::<Test>(&contract_a(), &contract_b(), 10),
+            "Caller doesn't have enough funds"
+        );
+        assert_err!(
+            send_c2c_tx::<Test>(&contract_a(), &contract_b(), 10, &vec![1, 2, 3, 4], 100),
+            "Caller doesn't have enough funds"
+        );
+        assert_eq!(mock_utxo(&utxo_b), Some((contract_b(), 10)));
+        assert_eq!(
+            Pp::contract_balance(&contract_b()),
+            Some((10, vec![(utxo_b, 10)]))
+        );
+    })
+}
+
+#[test]
+fn spent_utxos_leave_the_balance() {
+    new_test_ext().execute_with(|| {
+        new_contract(&contract_a());
+        let funding = fund_contract(&contract_a(), 10, true);
+        // a utxo paid to the contract without funding it is owned, but not part of the funds.
+        let owned = fund_contract(&contract_a(), 3, false);
+        assert_eq!(
+            Pp::contract_balance(&contract_a()),
+            Some((10, vec![(funding, 10), (owned, 3)]))
+        );
+
+        assert_ok!(Pp::on_utxo_spent(&contract_a(), owned, 3, None, false));
+        assert_eq!(
+            Pp::contract_balance(&contract_a()),
+            Some((10, vec![(funding, 10)]))
+        );
+
+        assert_err!(
+            Pp::on_utxo_spent(&contract_a(), owned, 3, None, false),
+            "Contract doesn't own the UTXO"
+        );
+        assert_err!(
+            Pp::on_utxo_spent(&contract_b(), funding, 10, None, true),
+            "Contract doesn't exist!"
+        );
+
+        assert_ok!(Pp::on_utxo_spent(&contract_a(), funding, 10, None, true));
+        assert_eq!(Pp::contract_balance(&contract_a()), Some((0, vec![])));
+    })
+}
+
+#[test]
+fn sweep_sends_everything_to_the_beneficiary() {
+    new_test_ext().execute_with(|| {
+        new_contract(&contract_a());
+        fund_contract(&contract_a(), 10, true);
+        fund_contract(&contract_a(), 3, false);
+        assert_eq!(Pp::contracts(), vec![(contract_a(), H256::zero())]);
+        let beneficiary = H256::repeat_byte(7);
+
+        assert_ok!(sweep_utxos::<Test>(
+            &contract_a(),
+            &Beneficiary::Pubkey(beneficiary)
+        ));
+
+        // the contract is gone, with all its utxos.
+        assert_eq!(Pp::contract_balance(&contract_a()), None);
+        assert_eq!(Pp::contract_creations(&contract_a()), None);
+        assert!(Pp::contracts().is_empty());
+        let beneficiary = AccountId::from(beneficiary.0);
+        let swept: Vec<_> = MOCK_UTXOS.with(|utxos| utxos.borrow().values().cloned().collect());
+        assert_eq!(swept, vec![(beneficiary, 13, false)]);
+        assert!(System::events().iter().any(|record| matches!(
+            &record.event,
+            Event::Pp(crate::Event::ContractTerminated(contract, new_utxos))
+                if *contract == contract_a() && new_utxos.len() == 1
+        )));
+
+        assert_err!(
+            sweep_utxos::<Test>(&contract_a(), &Beneficiary::Pubkey(H256::zero())),
+            "Contract doesn't exist!"
+        );
+    })
+}
+
+#[test]
+fn any_contract_gone_without_sweeping_is_found() {
+    new_test_ext().execute_with(|| {
+        // the mock contracts don't exist in `pallet_contracts`, as after a `seal_terminate`.
+        new_contract(&contract_a());
+        new_contract(&contract_b());
+        assert!(terminated_without_sweeping::<Test>().is_some());
+
+        assert_ok!(sweep_utxos::<Test>(
+            &contract_a(),
+            &Beneficiary::Pubkey(H256::zero())
+        ));
+        assert_eq!(terminated_without_sweeping::<Test>(), Some(contract_b()));
+
+        assert_ok!(sweep_utxos::<Test>(
+            &contract_b(),
+            &Beneficiary::Pubkey(H256::zero())
+        ));
+        assert_eq!(terminated_without_sweeping::<Test>(), None);
+    })
+}
@@ -16,8 +16,30 @@
 // Author(s): A. Altonen, Anton Sinitsyn
 #![cfg_attr(not(feature = "std"), no_std)]
 
+use pallet_utxo::{
+    supply::Supply,
+    tokens::{TokenId, Value},
+};
+use sp_core::H256;
+use sp_runtime::traits::NumberFor;
+
 sp_api::decl_runtime_apis! {
+    /// Version 2 adds the queries of utxo maturity, supply and block reward.
+    #[api_version(2)]
     pub trait UtxoApi {
         fn send() -> u32;
+
+        /// Returns the block number from which the given block reward utxo can be spent.
+        fn utxo_maturity(outpoint: H256) -> Option<NumberFor<Block>>;
+
+        /// Returns the total supply of MLT coins.
+        fn mlt_supply() -> Supply;
+
+        /// Returns the total supply of the given token, if it was ever issued.
+        fn token_supply(token_id: TokenId) -> Option<Supply>;
+
+        /// Returns the block reward at the given block number, following the current
+        /// emission schedule. Transaction fees are not included.
+        fn block_reward(block_number: NumberFor<Block>) -> Value;
     }
 }
@@ -17,16 +17,43 @@
 
 use jsonrpc_core::{Error as RpcError, ErrorCode, Result};
 use jsonrpc_derive::rpc;
+use pallet_utxo::{
+    supply::Supply,
+    tokens::{TokenId, Value},
+};
 pub use pallet_utxo_rpc_runtime_api::UtxoApi as UtxoRuntimeApi;
-use sp_api::ProvideRuntimeApi;
+use sp_api::{ApiExt, ProvideRuntimeApi};
 use sp_blockchain::HeaderBackend;
-use sp_runtime::{generic::BlockId, traits::Block as BlockT};
+use sp_core::H256;
+use sp_runtime::{
+    generic::BlockId,
+    traits::{Block as BlockT, NumberFor},
+};
 use std::sync::Arc;
 
 #[rpc]
-pub trait UtxoApi<BlockHash> {
+pub trait UtxoApi<BlockHash, BlockNumber> {
     #[rpc(name = "utxo_send")]
     fn send(&self, at: Option<BlockHash>) -> Result<u32>;
+
+    /// Returns the block number from which the given block reward utxo can be spent.
+    /// `None` if the utxo has no maturity period.
+    #[rpc(name = "utxo_maturity")]
+    fn utxo_maturity(&self, outpoint: H256, at: Option<BlockHash>) -> Result<Option<BlockNumber>>;
+
+    /// Returns how much MLT has been issued, and how much of it is circulating, locked or burned.
+    #[rpc(name = "utxo_mlt_supply")]
+    fn mlt_supply(&self, at: Option<BlockHash>) -> Result<Supply>;
+
+    /// Returns the supply of the token with the given base58 token id.
+    /// `None` if the token was never issued.
+    #[rpc(name = "utxo_token_supply")]
+    fn token_supply(&self, token_id: String, at: Option<BlockHash>) -> Result<Option<Supply>>;
+
+    /// Returns the block reward at the given block number, based on the current emission schedule.
+    /// Transaction fees are not included.
+    #[rpc(name = "utxo_block_reward")]
+    fn block_reward(&self, block_number: BlockNumber, at: Option<BlockHash>) -> Result<Value>;
 }
 
 /// A struct that implements the [`UtxoApi`].
@@ -53,9 +80,35 @@ pub enum Error {
     RuntimeError = 2,
     /// The access to Storage failed
     StorageError = 3,
+    /// The runtime doesn't support the method.
+    UnsupportedError = 4,
+}
+
+/// Fails if the runtime at `at` is older than version 2 of the runtime API,
+/// which added all the queries but `send`.
+fn ensure_api_v2<Block, Api>(api: &Api, at: &BlockId<Block>) -> Result<()>
+where
+    Block: BlockT,
+    Api: ApiExt<Block> + UtxoRuntimeApi<Block>,
+{
+    let supported = api
+        .has_api_with::<dyn UtxoRuntimeApi<Block>, _>(at, |version| version >= 2)
+        .map_err(|e| RpcError {
+            code: ErrorCode::ServerError(Error::RuntimeError as i64),
+            message: "Unable to query the runtime API version.".into(),
+            data: Some(format!("{:?}", e).into()),
+        })?;
+    if !supported {
+        return Err(RpcError {
+            code: ErrorCode::ServerError(Error::UnsupportedError as i64),
+            message: "The runtime doesn't support this method.".into(),
+            data: None,
+        });
+    }
+    Ok(())
 }
 
-impl<C, Block> UtxoApi<<Block as BlockT>::Hash> for Utxo<C, Block>
+impl<C, Block> UtxoApi<<Block as BlockT>::Hash, NumberFor<Block>> for Utxo<C, Block>
 where
     Block: BlockT,
     C: Send + Sync + 'static,
@@ -75,4 +128,78 @@ where
             data: Some(format!("{:?}", e).into()),
         })
     }
+
+    fn utxo_maturity(
+        &self,
+        outpoint: H256,
+        at: Option<<Block as BlockT>::Hash>,
+    ) -> Result<Option<NumberFor<Block>>> {
+        let api = self.client.runtime_api();
+        let at = BlockId::hash(at.unwrap_or_else(||
+            // If the block hash is not supplied assume the best block.
+            self.client.info().best_hash));
+        ensure_api_v2(&*api, &at)?;
+
+        api.utxo_maturity(&at, outpoint).map_err(|e| RpcError {
+            code: ErrorCode::ServerError(Error::RuntimeError as i64),
+            message: "Unable to query the utxo maturity.".into(),
+            data: Some(format!("{:?}", e).into()),
+        })
+    }
+
+    fn mlt_supply(&self, at: Option<<Block as BlockT>::Hash>) -> Result<Supply> {
+        let api = self.client.runtime_api();
+        let at = BlockId::hash(at.unwrap_or_else(||
+            // If the block hash is not supplied assume the best block.
+            self.client.info().best_hash));
+        ensure_api_v2(&*api, &at)?;
+
+        api.mlt_supply(&at).map_err(|e| RpcError {
+            code: ErrorCode::ServerError(Error::RuntimeError as i64),
+            message: "Unable to query the MLT supply.".into(),
+            data: Some(format!("{:?}", e).into()),
+        })
+    }
+
+    fn token_supply(
+        &self,
+        token_id: String,
+        at: Option<<Block as BlockT>::Hash>,
+    ) -> Result<Option<Supply>> {
+        let token_id = TokenId::from_string(&token_id).map_err(|e| RpcError {
+            code: ErrorCode::ServerError(Error::DecodeError as i64),
+            message: "Unable to decode the token id.".into(),
+            data: Some(e.into()),
+        })?;
+
+        let api = self.client.runtime_api();
+        let at = BlockId::hash(at.unwrap_or_else(||
+            // If the block hash is not supplied assume the best block.
+            self.client.info().best_hash));
+        ensure_api_v2(&*api, &at)?;
+
+        api.token_supply(&at, token_id).map_err(|e| RpcError {
+            code: ErrorCode::ServerError(Error::RuntimeError as i64),
+            message: "Unable to query the token supply.".into(),
+            data: Some(format!("{:?}", e).into()),
+        })
+    }
+
+    fn block_reward(
+        &self,
+        block_number: NumberFor<Block>,
+        at: Option<<Block as BlockT>::Hash>,
+    ) -> Result<Value> {
+        let api = self.client.runtime_api();
+        let at = BlockId::hash(at.unwrap_or_else(||
+            // If the block hash is not supplied assume the best block.
+            self.client.info().best_hash));
+        ensure_api_v2(&*api, &at)?;
+
+        api.block_reward(&at, block_number).map_err(|e| RpcError {
+            code: ErrorCode::ServerError(Error::RuntimeError as i64),
+            message: "Unable to query the block reward.".into(),
+            data: Some(format!("{:?}", e).into()),
+        })
+    }
 }
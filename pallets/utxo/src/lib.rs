@@ -21,6 +21,7 @@ pub use pallet::*;
 
 #[cfg(feature = "runtime-benchmarks")]
 mod benchmarking;
+pub mod migrations;
 #[cfg(test)]
 mod mock;
 mod rewards;
@@ -29,42 +30,51 @@ mod sign;
 pub mod staking;
 #[cfg(test)]
 mod staking_tests;
+pub mod supply;
 #[cfg(test)]
 mod tests;
 pub mod tokens;
 pub mod weights;
 
-use chainscript::Builder;
+use crate::tokens::{OutputData, TokenId, Value};
 use codec::Encode;
 use core::convert::TryInto;
 use frame_support::{
     inherent::Vec,
     pallet_prelude::{DispatchError, DispatchResultWithPostInfo},
+    storage::{with_transaction, TransactionOutcome},
+    traits::UnixTime,
+    weights::Weight,
 };
-use sp_core::{crypto::UncheckedFrom, H256, H512};
-use sp_runtime::sp_std::vec;
-use utxo_api::UtxoApi;
+use sp_core::{crypto::UncheckedFrom, H160, H256, H512};
+use sp_runtime::sp_std::{collections::btree_map::BTreeMap, vec};
+use sp_runtime::traits::{BlakeTwo256, Hash};
+use utxo_api::{Beneficiary, UtxoApi};
 
 #[frame_support::pallet]
 pub mod pallet {
     use crate::rewards::reward_block_author;
+    pub use crate::rewards::{EmissionSchedule, MissingAuthorPolicy, RewardDistribution};
     pub use crate::script::{BlockTime, RawBlockTime};
     use crate::sign::{self, Scheme};
     // todo: This part isn't fully tested, left for the next PR
     // use crate::tokens::{NftDataHash};
+    pub use crate::staking::{SlashPolicy, ValidatorPreferences};
     use crate::staking::{self, StakingHelper};
+    use crate::supply::{self, Supply};
     use crate::tokens::{OutputData, TokenId, Value};
     use bech32;
     use chainscript::Script;
     use codec::{Decode, Encode};
     use core::marker::PhantomData;
     use frame_support::{
-        dispatch::{DispatchResultWithPostInfo, Vec},
+        dispatch::{DispatchErrorWithPostInfo, DispatchResultWithPostInfo, Vec},
         pallet_prelude::*,
         sp_io::crypto,
-        sp_runtime::traits::{BlakeTwo256, Dispatchable, Hash},
+        sp_runtime::traits::{BlakeTwo256, Dispatchable, Hash, Saturating},
         sp_runtime::Percent,
-        traits::{IsSubType, UnixTime},
+        traits::{IsSubType, StorageVersion, UnixTime},
+        transactional,
     };
     use frame_system::pallet_prelude::*;
     use hex_literal::hex;
@@ -76,7 +86,7 @@ pub mod pallet {
         sp_std::{convert::TryInto, str, vec},
         sr25519,
         testing::SR25519,
-        H256, H512,
+        H160, H256, H512,
     };
     pub const MLT_UNIT: Value = 1_000 * 100_000_000;
 
@@ -137,8 +147,12 @@ pub mod pallet {
         FundsAtUnlockedState,
     }
 
+    /// The storage version. see `migrations.rs`.
+    pub(crate) const STORAGE_VERSION: StorageVersion = StorageVersion::new(1);
+
     #[pallet::pallet]
     #[pallet::generate_store(pub(super) trait Store)]
+    #[pallet::storage_version(STORAGE_VERSION)]
     pub struct Pallet<T>(PhantomData<T>);
 
     /// runtime configuration
@@ -153,30 +167,70 @@ pub mod pallet {
 
         type ProgrammablePool: ProgrammablePoolApi<AccountId = Self::AccountId>;
 
-        /// the rate of diminishing reward
+        /// how much MLT is paid for each unit of gas given to a smart contract.
+        #[pallet::constant]
+        type GasPrice: Get<Value>;
+
+        /// the initial block reward, until the `RewardSchedule` is changed.
         #[pallet::constant]
         type InitialReward: Get<Value>;
 
-        /// the rate of diminishing reward
+        /// the minimum block reward, until the `RewardSchedule` is changed.
         #[pallet::constant]
         type DefaultMinimumReward: Get<Value>;
 
-        /// the rate of diminishing reward
+        /// the rate of diminishing reward, until the `RewardSchedule` is changed.
         #[pallet::constant]
         type RewardReductionFraction: Get<Percent>;
 
-        /// duration of unchanged rewards, before applying the RewardReductionFraction
+        /// duration of unchanged rewards, before applying the RewardReductionFraction.
+        /// Used until the `RewardSchedule` is changed.
         #[pallet::constant]
         type RewardReductionPeriod: Get<Self::BlockNumber>;
 
+        /// how the reward of each block is divided between the author, the stakers
+        /// and the treasury.
+        #[pallet::constant]
+        type RewardDistribution: Get<RewardDistribution>;
+
+        /// the fraction of the block reward given to the author of each uncle
+        /// included in a block.
+        #[pallet::constant]
+        type UncleRewardFraction: Get<Percent>;
+
+        /// uncles older than this number of blocks are not rewarded.
+        #[pallet::constant]
+        type MaxUncleAge: Get<Self::BlockNumber>;
+
+        /// where the reward goes, when the block author could not be found.
+        #[pallet::constant]
+        type MissingAuthorPolicy: Get<MissingAuthorPolicy>;
+
+        /// number of blocks a block reward utxo has to wait, before it can be spent.
+        #[pallet::constant]
+        type RewardMaturity: Get<Self::BlockNumber>;
+
         /// the minimum value for initial staking.
         #[pallet::constant]
         type MinimumStake: Get<Value>;
 
+        /// the minimum value for nominating validators.
+        #[pallet::constant]
+        type MinimumNominatorStake: Get<Value>;
+
         /// how much are we charging for withdrawing the unlocked stake.
         #[pallet::constant]
         type StakeWithdrawalFee: Get<Value>;
 
+        /// the maximum number of nominators rewarded for each validator, in an era.
+        /// Has to be the same as the one of the `StakingHelper`'s logic.
+        #[pallet::constant]
+        type MaxNominatorRewardedPerValidator: Get<u32>;
+
+        /// where the value slashed from the locked utxos goes.
+        #[pallet::constant]
+        type SlashPolicy: Get<SlashPolicy>;
+
         /// helps in dealing with staking, using the logic outside of this pallet.
         /// An example is the `pallet-staking`, where it deals with multiple operations
         /// that utxo will convert with only a few. see the trait at pallet-utxo's `staking.rs`.
@@ -191,6 +245,14 @@ pub mod pallet {
         fn send_to_address(u: u32) -> Weight;
         fn unlock_request_for_withdrawal(u: u32) -> Weight;
         fn withdraw_stake(u: u32) -> Weight;
+        fn partial_unlock_request_for_withdrawal() -> Weight;
+        fn set_emission_schedule(u: u32) -> Weight;
+        fn payout_stakers(n: u32) -> Weight;
+        fn set_staking_reward_destination(u: u32) -> Weight;
+        fn set_validator_preferences() -> Weight;
+        fn rotate_session_keys(k: u32) -> Weight;
+        fn change_controller() -> Weight;
+        fn rebond() -> Weight;
     }
 
     /// Transaction input
@@ -263,11 +325,11 @@ pub mod pallet {
     pub enum Destination<AccountId> {
         /// Plain pay-to-pubkey
         Pubkey(sr25519::Public),
-        /// Pay to fund a new programmable pool. Takes code and data.
-        CreatePP(Vec<u8>, Vec<u8>),
+        /// Pay to fund a new programmable pool. Takes code, data and the gas limit.
+        CreatePP(Vec<u8>, Vec<u8>, Weight),
         /// Pay to an existing contract. Takes a destination account,
-        /// whether the call funds the contract, and input data.
-        CallPP(AccountId, bool, Vec<u8>),
+        /// whether the call funds the contract, input data and the gas limit.
+        CallPP(AccountId, bool, Vec<u8>, Weight),
         /// Pay to script hash
         ScriptHash(H256),
         /// First attempt of staking.
@@ -276,6 +338,7 @@ pub mod pallet {
             stash_account: AccountId,
             controller_account: AccountId,
             session_key: Vec<u8>,
+            validator_prefs: ValidatorPreferences,
         },
         /// lock more funds
         /// The controller_account should be the EXACT one for the stash_account,
@@ -284,6 +347,12 @@ pub mod pallet {
             stash_account: AccountId,
             controller_account: AccountId,
         },
+        /// Nominate validators, instead of validating.
+        /// The stash account is also its own controller. see pallet-staking
+        LockForNominating {
+            stash_account: AccountId,
+            targets: Vec<AccountId>,
+        },
     }
 
     impl<AccountId> Destination<AccountId> {
@@ -339,6 +408,24 @@ pub mod pallet {
             stash_account: AccountId,
             controller_account: AccountId,
             session_key: Vec<u8>,
+        ) -> Self {
+            Self::new_lock_for_staking_with_prefs(
+                value,
+                stash_account,
+                controller_account,
+                session_key,
+                ValidatorPreferences::default(),
+            )
+        }
+
+        /// Create a new stake for the first time, with the given validator preferences.
+        /// see `new_lock_for_staking`.
+        pub fn new_lock_for_staking_with_prefs(
+            value: Value,
+            stash_account: AccountId,
+            controller_account: AccountId,
+            session_key: Vec<u8>,
+            validator_prefs: ValidatorPreferences,
         ) -> Self {
             Self {
                 value,
@@ -346,6 +433,7 @@ pub mod pallet {
                     stash_account,
                     controller_account,
                     session_key,
+                    validator_prefs,
                 },
                 data: None,
             }
@@ -367,11 +455,36 @@ pub mod pallet {
             }
         }
 
+        /// Create a new stake, to nominate the given validators.
+        /// # Arguments
+        /// * `value` - the amount to stake. It only has to be more than the `MinimumNominatorStake`.
+        /// * `stash_account` - holds the funds, and acts as its own controller.
+        /// * `targets` - the stash accounts of the validators to nominate.
+        pub fn new_lock_for_nominating(
+            value: Value,
+            stash_account: AccountId,
+            targets: Vec<AccountId>,
+        ) -> Self {
+            Self {
+                value,
+                destination: Destination::LockForNominating {
+                    stash_account,
+                    targets,
+                },
+                data: None,
+            }
+        }
+
         /// Create a new output to create a smart contract.
-        pub fn new_create_pp(value: Value, code: Vec<u8>, data: Vec<u8>) -> Self {
+        pub fn new_create_pp(
+            value: Value,
+            code: Vec<u8>,
+            data: Vec<u8>,
+            gas_limit: Weight,
+        ) -> Self {
             Self {
                 value,
-                destination: Destination::CreatePP(code, data),
+                destination: Destination::CreatePP(code, data, gas_limit),
                 data: None,
             }
         }
@@ -382,10 +495,11 @@ pub mod pallet {
             dest_account: AccountId,
             fund: bool,
             input: Vec<u8>,
+            gas_limit: Weight,
         ) -> Self {
             Self {
                 value,
-                destination: Destination::CallPP(dest_account, fund, input),
+                destination: Destination::CallPP(dest_account, fund, input, gas_limit),
                 data: None,
             }
         }
@@ -408,6 +522,29 @@ pub mod pallet {
                 data: Some(data),
             }
         }
+
+        /// Create a new output with the data field, to fund a smart contract without calling it.
+        pub fn new_call_pp_with_data(
+            value: Value,
+            dest_account: AccountId,
+            data: OutputData,
+        ) -> Self {
+            Self {
+                value,
+                destination: Destination::CallPP(dest_account, true, Vec::new(), 0),
+                data: Some(data),
+            }
+        }
+
+        /// The id and the amount of the tokens transferred by this output, if any.
+        pub(crate) fn token_transfer(&self) -> Option<(TokenId, Value)> {
+            match &self.data {
+                Some(OutputData::TokenTransferV1 { token_id, amount }) => {
+                    Some((token_id.clone(), *amount))
+                }
+                Some(OutputData::TokenIssuanceV1 { .. }) | None => None,
+            }
+        }
     }
 
     pub fn convert_to_h256<T: Config>(account: &T::AccountId) -> Result<H256, DispatchError> {
@@ -476,14 +613,47 @@ pub mod pallet {
     #[pallet::getter(fn reward_total)]
     pub(super) type RewardTotal<T> = StorageValue<_, Value, ValueQuery>;
 
+    #[pallet::type_value]
+    pub(super) fn DefaultRewardSchedule<T: Config>() -> EmissionSchedule<T::BlockNumber> {
+        EmissionSchedule::Reduction {
+            initial_reward: T::InitialReward::get(),
+            minimum_reward: T::DefaultMinimumReward::get(),
+            reduction_fraction: T::RewardReductionFraction::get(),
+            reduction_period: T::RewardReductionPeriod::get(),
+        }
+    }
+
+    /// how the block reward changes over time. Can only be changed by root.
+    #[pallet::storage]
+    #[pallet::getter(fn reward_schedule)]
+    pub(super) type RewardSchedule<T: Config> = StorageValue<
+        _,
+        EmissionSchedule<T::BlockNumber>,
+        ValueQuery,
+        DefaultRewardSchedule<T>,
+    >;
+
     #[pallet::storage]
     #[pallet::getter(fn current_block_author)]
     pub(super) type BlockAuthor<T> = StorageValue<_, H256, OptionQuery>;
 
+    /// the authors of the uncles included in the current block, with the age of each uncle.
+    #[pallet::storage]
+    #[pallet::getter(fn uncle_authors)]
+    pub(super) type UncleAuthors<T: Config> =
+        StorageValue<_, Vec<(H256, T::BlockNumber)>, ValueQuery>;
+
     #[pallet::storage]
     #[pallet::getter(fn utxo_store)]
     pub(super) type UtxoStore<T: Config> = StorageMap<_, Identity, H256, TransactionOutputFor<T>>;
 
+    /// The block number at which a block reward utxo was created.
+    /// The utxo cannot be spent until `RewardMaturity` blocks have passed since then.
+    #[pallet::storage]
+    #[pallet::getter(fn reward_utxo_height)]
+    pub(super) type RewardUtxoHeight<T: Config> =
+        StorageMap<_, Identity, H256, T::BlockNumber, OptionQuery>;
+
     #[pallet::storage]
     #[pallet::getter(fn token_issuance_transactions)]
     pub(super) type TokenIssuanceTransactions<T: Config> =
@@ -502,7 +672,7 @@ pub mod pallet {
     // pub(super) type NftUniqueDataHash<T: Config> =
     //     StorageMap<_, Identity, NftDataHash, /* UTXO */ H256, OptionQuery>;
 
-    /// Represents the validators' stakes. When a validator chooses to stop validating,
+    /// Represents the validators' and nominators' stakes. When a validator chooses to stop validating,
     /// the utxo here is transferred back to `UtxoStore`.
     #[pallet::storage]
     #[pallet::getter(fn locked_utxos)]
@@ -515,6 +685,34 @@ pub mod pallet {
     pub(super) type StakingCount<T: Config> =
         StorageMap<_, Identity, T::AccountId, (u64, Value), OptionQuery>;
 
+    /// the value of the stake waiting to be withdrawn, while the rest stays locked.
+    #[pallet::storage]
+    #[pallet::getter(fn partially_unlocked_stake)]
+    pub(super) type PartiallyUnlockedStake<T: Config> =
+        StorageMap<_, Identity, T::AccountId, Value, OptionQuery>;
+
+    /// where the era rewards of a stash account should go, instead of its own pubkey.
+    #[pallet::storage]
+    #[pallet::getter(fn staking_reward_destination)]
+    pub(super) type StakingRewardDestination<T: Config> =
+        StorageMap<_, Identity, T::AccountId, Destination<T::AccountId>, OptionQuery>;
+
+    /// the total supply of MLT coins.
+    #[pallet::storage]
+    #[pallet::getter(fn mlt_supply)]
+    pub(super) type MltSupply<T> = StorageValue<_, Supply, ValueQuery>;
+
+    /// the total supply of each issued token.
+    #[pallet::storage]
+    #[pallet::getter(fn token_supply)]
+    pub(super) type TokenSupply<T> = StorageMap<_, Identity, TokenId, Supply, OptionQuery>;
+
+    /// the transactions made by smart contracts during a dry run of a contract call.
+    /// Only present while the dry run is in progress, which is never committed.
+    #[pallet::storage]
+    pub(super) type DryRunTransactions<T: Config> =
+        StorageValue<_, Vec<TransactionFor<T>>, OptionQuery>;
+
     #[pallet::event]
     #[pallet::generate_deposit(pub(super) fn deposit_event)]
     #[pallet::metadata(T::AccountId = "AccountId")]
@@ -525,13 +723,62 @@ pub mod pallet {
         /// \[utxo_for_block_author\]
         BlockAuthorRewarded(TransactionOutput<T::AccountId>),
 
+        /// A staker has been given its share of the block reward.
+        /// \[utxo_for_staker, stash_account\]
+        StakerRewarded(TransactionOutput<T::AccountId>, T::AccountId),
+
+        /// The treasury has been given its share of the block reward.
+        /// \[utxo_for_treasury\]
+        TreasuryRewarded(TransactionOutput<T::AccountId>),
+
+        /// The author of an uncle has been rewarded with MLT Coins.
+        /// \[utxo_for_uncle_author\]
+        UncleAuthorRewarded(TransactionOutput<T::AccountId>),
+
+        /// The block author could not be found. The reward goes to the `MissingAuthorPolicy`.
+        /// \[reward\]
+        BlockAuthorMissing(Value),
+
         /// Unstaking is enabled after the end of bonding duration, as set in pallet-staking.
         /// \[stash_account\]
         StakeUnlocked(T::AccountId),
 
+        /// Part of the stake will be available for withdrawal after the end of bonding duration.
+        /// The stash account keeps on validating with the rest.
+        /// \[unlocked_value, stash_account\]
+        StakePartiallyUnlocked(Value, T::AccountId),
+
+        /// The locked utxos of the stash account have been slashed.
+        /// \[slashed_value, stash_account\]
+        StakeSlashed(Value, T::AccountId),
+
+        /// The unlocked stake has been locked back, without being withdrawn.
+        /// \[stash_account\]
+        StakeRebonded(T::AccountId),
+
         /// Unlocked stake has been withdrawn.
         /// \[total_stake, stash_account\]
         StakeWithdrawn(Value, T::AccountId),
+
+        /// The era reward of a validator or a nominator has been paid out as a utxo.
+        /// \[utxo_for_staker, stash_account, era\]
+        EraRewardPaid(TransactionOutput<T::AccountId>, T::AccountId, u32),
+
+        /// The validator has new preferences.
+        /// \[stash_account, validator_preferences\]
+        ValidatorPreferencesChanged(T::AccountId, ValidatorPreferences),
+
+        /// The validator has new session keys.
+        /// \[stash_account\]
+        SessionKeysRotated(T::AccountId),
+
+        /// The stash account has a new controller account.
+        /// \[stash_account, controller_account\]
+        ControllerChanged(T::AccountId, T::AccountId),
+
+        /// The block reward will follow the new schedule.
+        /// \[emission_schedule\]
+        EmissionScheduleChanged(EmissionSchedule<T::BlockNumber>),
     }
 
     #[pallet::hooks]
@@ -539,6 +786,33 @@ pub mod pallet {
         fn on_finalize(block_num: T::BlockNumber) {
             reward_block_author::<T>(block_num);
         }
+
+        fn on_runtime_upgrade() -> Weight {
+            crate::migrations::migrate_to_v1::<T>()
+        }
+
+        #[cfg(feature = "try-runtime")]
+        fn pre_upgrade() -> Result<(), &'static str> {
+            crate::migrations::pre_migrate_to_v1::<T>()
+        }
+
+        #[cfg(feature = "try-runtime")]
+        fn post_upgrade() -> Result<(), &'static str> {
+            crate::migrations::post_migrate_to_v1::<T>()?;
+
+            let mut contracts = Vec::new();
+            for utxo in <UtxoStore<T>>::iter_values() {
+                if let Destination::CallPP(dest, _, _, _) = utxo.destination {
+                    if !contracts.contains(&dest) {
+                        contracts.push(dest);
+                    }
+                }
+            }
+            for contract in contracts.iter() {
+                check_contract_balance::<T>(contract)?;
+            }
+            Ok(())
+        }
     }
 
     // todo: This part isn't fully tested, left for the next PR
@@ -560,47 +834,153 @@ pub mod pallet {
         trx.encode()
     }
 
+    /// Instantiates the smart contract of a `CreatePP` output,
+    /// and returns the new contract account along with the gas consumed.
+    /// The whole transaction fails if the instantiation does.
+    /// The events of the contract are deposited by the programmable pool.
     pub fn create<T: Config>(
-        _caller: &T::AccountId,
-        _code: &Vec<u8>,
-        _utxo_hash: H256,
-        _utxo_value: u128,
-        _data: &Vec<u8>,
-    ) {
-        // let weight: Weight = 6000000000;
-
-        // match T::ProgrammablePool::create(caller, weight, code, utxo_hash, utxo_value, data) {
-        //     Ok(_) => log::info!("success!"),
-        //     Err(e) => log::error!("failure: {:#?}", e),
-        // }
+        caller: &T::AccountId,
+        code: &Vec<u8>,
+        utxo_hash: H256,
+        utxo_value: Value,
+        data: &Vec<u8>,
+        gas_limit: Weight,
+    ) -> Result<(T::AccountId, Weight), DispatchError> {
+        let (contract, gas_consumed) =
+            T::ProgrammablePool::create(caller, gas_limit, code, utxo_hash, utxo_value, data)
+                .map_err(|e| {
+                    log::error!("failed to create the smart contract: {:?}", e);
+                    DispatchError::Other(e)
+                })?;
+        Ok((contract, gas_consumed))
     }
 
+    /// Calls the smart contract of a `CallPP` output, and returns the gas consumed.
+    /// The whole transaction fails if the call does, or if the contract reverts.
     pub fn call<T: Config>(
-        _caller: &T::AccountId,
-        _dest: &T::AccountId,
-        _utxo_hash: H256,
-        _utxo_value: u128,
-        _fund_contract: bool,
-        _data: &Vec<u8>,
-    ) {
-        // let weight: Weight = 6000000000;
-
-        // match T::ProgrammablePool::call(
-        //     caller,
-        //     dest,
-        //     weight,
-        //     utxo_hash,
-        //     utxo_value,
-        //     fund_contract,
-        //     data,
-        // ) {
-        //     Ok(_) => log::info!("success!"),
-        //     Err(e) => log::error!("failure: {:#?}", e),
-        // }
+        caller: &T::AccountId,
+        dest: &T::AccountId,
+        utxo_hash: H256,
+        utxo_value: Value,
+        utxo_token: Option<(TokenId, Value)>,
+        fund_contract: bool,
+        data: &Vec<u8>,
+        gas_limit: Weight,
+    ) -> Result<Weight, DispatchError> {
+        let gas_consumed = T::ProgrammablePool::call(
+            caller,
+            dest,
+            gas_limit,
+            utxo_hash,
+            utxo_value,
+            utxo_token.map(|(token_id, amount)| (token_id.into(), amount)),
+            fund_contract,
+            data,
+        )
+        .map_err(|e| {
+            log::error!("failed to call the smart contract: {:?}", e);
+            DispatchError::Other(e)
+        })?;
+        Ok(gas_consumed)
+    }
+
+    /// The gas limit of all the smart contracts created or called by the transaction.
+    pub fn gas_limit<T: Config>(tx: &TransactionFor<T>) -> Weight {
+        tx.outputs
+            .iter()
+            .map(|output| match output.destination {
+                Destination::CreatePP(_, _, gas_limit)
+                | Destination::CallPP(_, _, _, gas_limit) => gas_limit,
+                _ => 0,
+            })
+            .fold(0, |total, gas_limit| total.saturating_add(gas_limit))
+    }
+
+    /// Checks that the balance of the smart contract matches the `CallPP` utxos paid to it:
+    /// it owns all of them, and its funds are the value of those funding it, in MLT and in
+    /// each token. Scans the whole `UtxoStore`, so it is only run by the tests and
+    /// by the `post_upgrade` check of try-runtime.
+    #[cfg(any(test, feature = "try-runtime"))]
+    pub fn check_contract_balance<T: Config>(contract: &T::AccountId) -> Result<(), &'static str> {
+        let (funds, mut owned) =
+            T::ProgrammablePool::contract_balance(contract).ok_or("contract doesn't exist")?;
+
+        let mut expected_funds: Value = 0;
+        let mut expected = Vec::new();
+        let mut expected_tokens: BTreeMap<TokenId, (Value, Vec<(H256, Value)>)> = BTreeMap::new();
+        for (hash, utxo) in <UtxoStore<T>>::iter() {
+            if let Destination::CallPP(dest, fund, _, _) = &utxo.destination {
+                if dest == contract {
+                    expected.push((hash, utxo.value));
+                    if *fund {
+                        expected_funds = expected_funds.saturating_add(utxo.value);
+                    }
+                    if let Some((token_id, amount)) = utxo.token_transfer() {
+                        let (token_funds, token_utxos) =
+                            expected_tokens.entry(token_id).or_insert((0, Vec::new()));
+                        token_utxos.push((hash, amount));
+                        if *fund {
+                            *token_funds = token_funds.saturating_add(amount);
+                        }
+                    }
+                }
+            }
+        }
+
+        owned.sort();
+        expected.sort();
+        ensure!(
+            owned == expected,
+            "contract utxos don't match the UtxoStore"
+        );
+        ensure!(
+            funds == expected_funds,
+            "contract funds don't match its utxos"
+        );
+
+        for (token_id, (expected_funds, mut expected)) in expected_tokens {
+            let (funds, mut owned) =
+                T::ProgrammablePool::contract_token_balance(contract, &token_id.into())
+                    .ok_or("contract tokens don't match the UtxoStore")?;
+            owned.sort();
+            expected.sort();
+            ensure!(
+                owned == expected,
+                "contract tokens don't match the UtxoStore"
+            );
+            ensure!(
+                funds == expected_funds,
+                "contract token funds don't match its utxos"
+            );
+        }
+        Ok(())
     }
 
+    /// How much MLT is paid for the given amount of gas.
+    pub fn gas_fee<T: Config>(gas: Weight) -> Value {
+        (gas as Value).saturating_mul(T::GasPrice::get())
+    }
+
+    /// Validates the transaction, as made by a user.
+    /// Once all its inputs are known, its fee has to pay for the gas of its smart contracts.
     pub fn validate_transaction<T: Config>(
         tx: &TransactionFor<T>,
+    ) -> Result<ValidTransaction, &'static str> {
+        let tx_validity = validate_transaction_with_context::<T>(tx, None)?;
+        if tx_validity.requires.is_empty() {
+            ensure!(
+                tx_validity.priority as Value >= gas_fee::<T>(gas_limit::<T>(tx)),
+                "insufficient fee to pay for the gas"
+            );
+        }
+        Ok(tx_validity)
+    }
+
+    /// Validates the transaction, as made by the given smart contract if any.
+    /// The `CallPP` utxos paid to a contract can only be spent by the contract itself.
+    pub(crate) fn validate_transaction_with_context<T: Config>(
+        tx: &TransactionFor<T>,
+        contract: Option<&T::AccountId>,
     ) -> Result<ValidTransaction, &'static str> {
         //ensure rather than assert to avoid panic
         //both inputs and outputs should contain at least 1 and at most u32::MAX - 1 entries
@@ -667,6 +1047,12 @@ pub mod pallet {
                         input.lock_hash() == *lock_commitment,
                         "Lock hash does not match"
                     );
+                    if let Some(maturity) = utxo_maturity::<T>(&input.outpoint) {
+                        ensure!(
+                            <frame_system::Pallet<T>>::block_number() >= maturity,
+                            "block reward utxo has not matured yet"
+                        );
+                    }
                     resolved.push(input_utxo);
                 } else {
                     missing.push(input.outpoint.clone().as_fixed_bytes().to_vec());
@@ -943,18 +1329,28 @@ pub mod pallet {
             new_utxos.push(hash.as_fixed_bytes().to_vec());
 
             match output.destination {
-                Destination::CreatePP(_, _) => {
+                Destination::CreatePP(_, _, _) => {
                     ensure!(!<UtxoStore<T>>::contains_key(hash), "output already exists");
+                    ensure!(
+                        output.data.is_none(),
+                        "tokens can't be used to create a smart contract"
+                    );
                     log::info!("TODO validate CreatePP as output");
                 }
-                Destination::CallPP(_, _, _) => {
+                Destination::CallPP(_, _, _, _) => {
                     ensure!(!<UtxoStore<T>>::contains_key(hash), "output already exists");
+                    ensure!(
+                        !matches!(output.data, Some(OutputData::TokenIssuanceV1 { .. })),
+                        "tokens can't be issued to a smart contract"
+                    );
                     log::info!("TODO validate CallPP as output");
                 }
                 Destination::Pubkey(_) | Destination::ScriptHash(_) => {
                     ensure!(!<UtxoStore<T>>::contains_key(hash), "output already exists");
                 }
-                Destination::LockForStaking { .. } | Destination::LockExtraForStaking { .. } => {
+                Destination::LockForStaking { .. }
+                | Destination::LockExtraForStaking { .. }
+                | Destination::LockForNominating { .. } => {
                     staking::validate_staking_ops::<T>(output, hash)?;
                 }
             }
@@ -1036,17 +1432,14 @@ pub mod pallet {
                             .verify(&msg);
                         ensure!(ok, "signature must be valid");
                     }
-                    Destination::CreatePP(_, _) => {
+                    Destination::CreatePP(_, _, _) => {
                         log::info!("TODO validate spending of OP_CREATE");
                     }
-                    Destination::CallPP(_, _, _) => {
-                        let spend =
-                            u16::from_le_bytes(input.witness[1..].try_into().or_else(|_| {
-                                Err(DispatchError::Other(
-                                    "Failed to convert witness to an opcode",
-                                ))
-                            })?);
-                        ensure!(spend == 0x1337, "OP_SPEND not found");
+                    Destination::CallPP(dest, _, _, _) => {
+                        ensure!(
+                            contract == Some(dest),
+                            "only the contract can spend its utxos"
+                        );
                     }
                     Destination::ScriptHash(_hash) => {
                         let witness = input.witness.clone();
@@ -1055,7 +1448,8 @@ pub mod pallet {
                             .map_err(|_| "script verification failed")?;
                     }
                     Destination::LockForStaking { .. }
-                    | Destination::LockExtraForStaking { .. } => {
+                    | Destination::LockExtraForStaking { .. }
+                    | Destination::LockForNominating { .. } => {
                         return Err("cannot spend a staking utxo.");
                     }
                 }
@@ -1081,23 +1475,41 @@ pub mod pallet {
 
     /// Update storage to reflect changes made by transaction
     /// Where each utxo key is a hash of the entire transaction and its order in the TransactionOutputs vector
+    /// Returns the gas consumed by the smart contracts created or called by the transaction.
     pub fn update_storage<T: Config>(
         caller: &T::AccountId,
         tx: &TransactionFor<T>,
         reward: Value,
-    ) -> DispatchResultWithPostInfo {
+    ) -> Result<Weight, DispatchErrorWithPostInfo> {
         // Calculate new reward total
         let new_total = <RewardTotal<T>>::get().checked_add(reward).ok_or("Reward overflow")?;
 
         log::debug!("update_storage:: reward total: {:?}", new_total);
         <RewardTotal<T>>::put(new_total);
 
+        // the spent token utxos are still needed to know how much was burned.
+        supply::note_token_transaction::<T>(tx);
+
         // Removing spent UTXOs
         for input in &tx.inputs {
             log::debug!("removing {:?} in UtxoStore.", input.outpoint);
-            <UtxoStore<T>>::remove(input.outpoint);
+            if let Some(utxo) = <UtxoStore<T>>::take(input.outpoint) {
+                // the spent utxo is not part of the contract's balance anymore
+                if let Destination::CallPP(contract, fund, _, _) = &utxo.destination {
+                    T::ProgrammablePool::on_utxo_spent(
+                        contract,
+                        input.outpoint,
+                        utxo.value,
+                        utxo.token_transfer().map(|(token_id, amount)| (token_id.into(), amount)),
+                        *fund,
+                    )
+                    .map_err(DispatchError::Other)?;
+                }
+            }
+            <RewardUtxoHeight<T>>::remove(input.outpoint);
         }
 
+        let mut gas_consumed: Weight = 0;
         for (index, output) in tx.outputs.iter().enumerate() {
             let hash = tx.outpoint(index as u64);
 
@@ -1136,15 +1548,31 @@ pub mod pallet {
                         Some(OutputData::TokenTransferV1 { .. }) | None => continue,
                     }
                 }
-                Destination::CreatePP(script, data) => {
-                    log::debug!("inserting to UtxoStore {:?} as key {:?}", output, hash);
-                    <UtxoStore<T>>::insert(hash, output);
-                    create::<T>(caller, script, hash, output.value, &data);
+                Destination::CreatePP(script, data, gas_limit) => {
+                    let (contract, gas) =
+                        create::<T>(caller, script, hash, output.value, &data, *gas_limit)?;
+                    gas_consumed = gas_consumed.saturating_add(gas);
+
+                    // the value funds the new contract, which spends it like its `CallPP` utxos.
+                    let utxo =
+                        TransactionOutput::new_call_pp(output.value, contract, true, Vec::new(), 0);
+                    log::debug!("inserting to UtxoStore {:?} as key {:?}", utxo, hash);
+                    <UtxoStore<T>>::insert(hash, utxo);
                 }
-                Destination::CallPP(acct_id, fund, data) => {
+                Destination::CallPP(acct_id, fund, data, gas_limit) => {
                     log::debug!("inserting to UtxoStore {:?} as key {:?}", output, hash);
                     <UtxoStore<T>>::insert(hash, output);
-                    call::<T>(caller, acct_id, hash, output.value, *fund, data);
+                    let gas = call::<T>(
+                        caller,
+                        acct_id,
+                        hash,
+                        output.value,
+                        output.token_transfer(),
+                        *fund,
+                        data,
+                        *gas_limit,
+                    )?;
+                    gas_consumed = gas_consumed.saturating_add(gas);
                 }
                 Destination::LockForStaking { .. } => {
                     staking::lock_for_staking::<T>(hash, output)?;
@@ -1152,20 +1580,110 @@ pub mod pallet {
                 Destination::LockExtraForStaking { .. } => {
                     staking::lock_extra_for_staking::<T>(hash, output)?;
                 }
+                Destination::LockForNominating { .. } => {
+                    staking::lock_for_nominating::<T>(hash, output)?;
+                }
             }
         }
 
-        Ok(().into())
+        Ok(gas_consumed)
+    }
+
+    /// Refunds the fee paid for the unused gas as a change utxo, to the destination
+    /// of the first input: it paid for the gas, unlike the signer that may only relay
+    /// the transaction. A destination that is not a pubkey or a script keeps the refund
+    /// in the fees.
+    fn refund_gas<T: Config>(
+        refund_destination: Option<Destination<T::AccountId>>,
+        tx: &TransactionFor<T>,
+        unused_gas: Weight,
+    ) -> DispatchResult {
+        let refund = gas_fee::<T>(unused_gas);
+        if refund == 0 {
+            return Ok(());
+        }
+
+        let destination = match refund_destination {
+            Some(destination @ Destination::Pubkey(_))
+            | Some(destination @ Destination::ScriptHash(_)) => destination,
+            _ => return Ok(()),
+        };
+        let utxo = TransactionOutput {
+            value: refund,
+            destination,
+            data: None,
+        };
+        let hash = BlakeTwo256::hash_of(&(&tx.outpoint(0), "gas_refund"));
+        ensure!(
+            !<UtxoStore<T>>::contains_key(hash),
+            Error::<T>::OutpointAlreadyExists
+        );
+
+        // the refund was counted in the fees of the transaction.
+        <RewardTotal<T>>::mutate(|total| *total = total.saturating_sub(refund));
+        log::debug!("inserting to UtxoStore {:?} as key {:?}", utxo, hash);
+        <UtxoStore<T>>::insert(hash, utxo);
+        Ok(())
     }
 
+    /// Returns the block number from which the given block reward utxo can be spent.
+    /// Utxos that are not block rewards have no maturity period.
+    pub fn utxo_maturity<T: Config>(outpoint: &H256) -> Option<T::BlockNumber> {
+        <RewardUtxoHeight<T>>::get(outpoint)
+            .map(|created_at| created_at.saturating_add(T::RewardMaturity::get()))
+    }
+
+    /// Validates and applies the transaction.
+    /// The gas of its smart contracts is paid out of the transaction fee,
+    /// and the unused gas is refunded to the owner of the first input.
+    /// Nothing is written if any of its outputs fails, e.g. a smart contract call.
+    /// Returns the actual weight, with only the gas consumed.
+    #[transactional]
     pub fn spend<T: Config>(
         caller: &T::AccountId,
         tx: &TransactionFor<T>,
     ) -> DispatchResultWithPostInfo {
+        // the fee paying for the gas is checked in the validation.
         let tx_validity = validate_transaction::<T>(tx)?;
         ensure!(tx_validity.requires.is_empty(), "missing inputs");
-        update_storage::<T>(caller, tx, tx_validity.priority as Value)?;
-        Ok(().into())
+        let fee = tx_validity.priority as Value;
+        let gas_limit = gas_limit::<T>(tx);
+        let refund_destination = tx
+            .inputs
+            .first()
+            .and_then(|input| <UtxoStore<T>>::get(&input.outpoint))
+            .map(|utxo| utxo.destination);
+
+        let gas_consumed = update_storage::<T>(caller, tx, fee)?;
+        refund_gas::<T>(
+            refund_destination,
+            tx,
+            gas_limit.saturating_sub(gas_consumed),
+        )?;
+
+        let spend_weight = <T as Config>::WeightInfo::spend(
+            tx.inputs.len().saturating_add(tx.outputs.len()) as u32,
+        );
+        Ok(Some(spend_weight.saturating_add(gas_consumed)).into())
+    }
+
+    /// Validates and applies a transaction made by a smart contract, on its behalf.
+    /// The gas of the called contracts is paid by the calling contract, out of its own gas.
+    /// Returns the gas consumed.
+    #[transactional]
+    pub(crate) fn spend_as_contract<T: Config>(
+        caller: &T::AccountId,
+        tx: &TransactionFor<T>,
+    ) -> Result<Weight, DispatchErrorWithPostInfo> {
+        let tx_validity = validate_transaction_with_context::<T>(tx, Some(caller))?;
+        ensure!(tx_validity.requires.is_empty(), "missing inputs");
+        let gas_consumed = update_storage::<T>(caller, tx, tx_validity.priority as Value)?;
+
+        // contract transactions are not extrinsics, so a dry run has to record them itself
+        if <DryRunTransactions<T>>::exists() {
+            <DryRunTransactions<T>>::append(tx);
+        }
+        Ok(gas_consumed)
     }
 
     /// Pick the UTXOs of `caller` from UtxoStore that satisfy request `value`
@@ -1206,14 +1724,23 @@ pub mod pallet {
 
     #[pallet::call]
     impl<T: Config> Pallet<T> {
-        #[pallet::weight(<T as Config>::WeightInfo::spend(tx.inputs.len().saturating_add(tx.outputs.len()) as u32))]
+        /// The weight includes the gas limits of the smart contracts the transaction calls,
+        /// and only the gas actually consumed is counted.
+        /// The transaction pays with its own fee, the difference of its inputs and outputs,
+        /// so nothing is charged to the signer.
+        #[pallet::weight((
+            <T as Config>::WeightInfo::spend(tx.inputs.len().saturating_add(tx.outputs.len()) as u32)
+                .saturating_add(gas_limit::<T>(tx)),
+            DispatchClass::Normal,
+            Pays::No
+        ))]
         pub fn spend(
             origin: OriginFor<T>,
             tx: Transaction<T::AccountId>,
         ) -> DispatchResultWithPostInfo {
-            spend::<T>(&ensure_signed(origin)?, &tx)?;
+            let post_info = spend::<T>(&ensure_signed(origin)?, &tx)?;
             Self::deposit_event(Event::<T>::TransactionSuccess(tx));
-            Ok(().into())
+            Ok(post_info)
         }
 
         #[pallet::weight(<T as Config>::WeightInfo::send_to_address(16_u32.saturating_add(address.len() as u32)))]
@@ -1293,6 +1820,26 @@ pub mod pallet {
             staking::unlock_request_for_withdrawal::<T>(ensure_signed(stash_origin)?)
         }
 
+        /// unlock only the given value of the stake using the STASH ACCOUNT.
+        /// Unlike `unlock_request_for_withdrawal`, the stash account keeps on validating,
+        /// as long as the rest of the stake is not less than the `MinimumStake`.
+        /// Withdraw the unlocked value with `withdraw_stake`.
+        #[pallet::weight(<T as Config>::WeightInfo::partial_unlock_request_for_withdrawal())]
+        pub fn partial_unlock_request_for_withdrawal(
+            stash_origin: OriginFor<T>,
+            value: Value,
+        ) -> DispatchResultWithPostInfo {
+            staking::partial_unlock_request_for_withdrawal::<T>(ensure_signed(stash_origin)?, value)
+        }
+
+        /// lock back the unlocked stake using the STASH ACCOUNT, before it is withdrawn.
+        /// If the whole stake was unlocked, the stash account goes back to validating or
+        /// nominating, as it was before the unlock.
+        #[pallet::weight(<T as Config>::WeightInfo::rebond())]
+        pub fn rebond(stash_origin: OriginFor<T>) -> DispatchResultWithPostInfo {
+            staking::rebond::<T>(ensure_signed(stash_origin)?)
+        }
+
         /// withdraw unlocked stake using the STASH ACCOUNT. Make sure the era for withdrawal has passed.
         /// If used with `pallet-staking`,it can be found in the ledger of datatype `StakingLedger`,
         /// the field `unlocking` of datatype `UnlockChunk`,
@@ -1301,6 +1848,77 @@ pub mod pallet {
         pub fn withdraw_stake(stash_origin: OriginFor<T>) -> DispatchResultWithPostInfo {
             staking::withdraw::<T>(ensure_signed(stash_origin)?)
         }
+
+        /// pays out the era rewards of the validator and its nominators as utxos.
+        /// Anyone can trigger the payout. Each era can only be paid out once.
+        #[pallet::weight(<T as Config>::WeightInfo::payout_stakers(
+            T::MaxNominatorRewardedPerValidator::get().saturating_add(1)
+        ))]
+        #[transactional]
+        pub fn payout_stakers(
+            origin: OriginFor<T>,
+            validator_stash: T::AccountId,
+            era: u32,
+        ) -> DispatchResultWithPostInfo {
+            ensure_signed(origin)?;
+            staking::payout_stakers::<T>(validator_stash, era)
+        }
+
+        /// sets where the era rewards will go, using the STASH ACCOUNT.
+        /// Only a `Pubkey` or a `ScriptHash` destination is allowed.
+        #[pallet::weight(<T as Config>::WeightInfo::set_staking_reward_destination(1 as u32))]
+        pub fn set_staking_reward_destination(
+            stash_origin: OriginFor<T>,
+            destination: Destination<T::AccountId>,
+        ) -> DispatchResultWithPostInfo {
+            staking::set_staking_reward_destination::<T>(ensure_signed(stash_origin)?, destination)
+        }
+
+        /// sets the commission and whether to accept nominations, using the STASH ACCOUNT.
+        /// Only validators can do this.
+        #[pallet::weight(<T as Config>::WeightInfo::set_validator_preferences())]
+        pub fn set_validator_preferences(
+            stash_origin: OriginFor<T>,
+            validator_prefs: ValidatorPreferences,
+        ) -> DispatchResultWithPostInfo {
+            staking::set_validator_preferences::<T>(ensure_signed(stash_origin)?, validator_prefs)
+        }
+
+        /// replaces the session keys using the STASH ACCOUNT, without having to unstake.
+        /// Generate the new session keys through an rpc call `author_rotateKeys`.
+        #[pallet::weight(<T as Config>::WeightInfo::rotate_session_keys(session_key.len() as u32))]
+        pub fn rotate_session_keys(
+            stash_origin: OriginFor<T>,
+            session_key: Vec<u8>,
+        ) -> DispatchResultWithPostInfo {
+            staking::rotate_session_keys::<T>(ensure_signed(stash_origin)?, session_key)
+        }
+
+        /// assigns a new controller account using the STASH ACCOUNT, without having to unstake.
+        #[pallet::weight(<T as Config>::WeightInfo::change_controller())]
+        pub fn change_controller(
+            stash_origin: OriginFor<T>,
+            controller_account: T::AccountId,
+        ) -> DispatchResultWithPostInfo {
+            staking::change_controller::<T>(ensure_signed(stash_origin)?, controller_account)
+        }
+
+        /// changes how the block reward is computed, starting from the current block.
+        #[pallet::weight(<T as Config>::WeightInfo::set_emission_schedule(match schedule {
+            EmissionSchedule::Steps(steps) => steps.len() as u32,
+            EmissionSchedule::Reduction { .. } => 1,
+        }))]
+        pub fn set_emission_schedule(
+            origin: OriginFor<T>,
+            schedule: EmissionSchedule<T::BlockNumber>,
+        ) -> DispatchResultWithPostInfo {
+            ensure_root(origin)?;
+            schedule.validate()?;
+
+            <RewardSchedule<T>>::put(schedule.clone());
+            Self::deposit_event(Event::<T>::EmissionScheduleChanged(schedule));
+            Ok(().into())
+        }
     }
 
     #[pallet::genesis_config]
@@ -1326,19 +1944,19 @@ pub mod pallet {
     impl<T: Config> GenesisBuild<T> for GenesisConfig<T> {
         fn build(&self) {
             self.genesis_utxos.iter().cloned().enumerate().for_each(|(index, u)| {
+                supply::note_mlt_issued::<T>(u.value);
                 // added the index and the `genesis` on the hashing, to indicate that these utxos are from the beginning of the chain.
                 UtxoStore::<T>::insert(BlakeTwo256::hash_of(&(&u, index as u64, "genesis")), u);
             });
 
             self.locked_utxos.iter().cloned().enumerate().for_each(|(index, u)| {
-                if let Destination::LockForStaking {
-                    stash_account,
-                    controller_account: _,
-                    session_key: _,
-                } = &u.destination
+                if let Destination::LockForStaking { stash_account, .. }
+                | Destination::LockForNominating { stash_account, .. } = &u.destination
                 {
                     <StakingCount<T>>::insert(stash_account.clone(), (1, u.value));
                 }
+                supply::note_mlt_issued::<T>(u.value);
+                supply::note_mlt_locked::<T>(u.value);
 
                 // added the index and the `genesis` on the hashing, to indicate that these utxos are from the beginning of the chain.
                 LockedUtxos::<T>::insert(BlakeTwo256::hash_of(&(&u, index as u64, "genesis")), u);
@@ -1352,6 +1970,14 @@ impl<T: Config> crate::Pallet<T> {
         1337
     }
 
+    pub fn utxo_maturity(outpoint: H256) -> Option<T::BlockNumber> {
+        crate::pallet::utxo_maturity::<T>(&outpoint)
+    }
+
+    pub fn block_reward(block_number: T::BlockNumber) -> crate::tokens::Value {
+        crate::rewards::get_block_author_reward::<T>(block_number)
+    }
+
     // todo: This part isn't fully tested, left for the next PR
     // pub fn nft_read(
     //     nft_id: &core::primitive::str,
@@ -1371,8 +1997,14 @@ impl<T: Config> crate::Pallet<T> {
     // }
 }
 
-fn coin_picker<T: Config>(outpoints: &Vec<H256>) -> Result<Vec<TransactionInput>, DispatchError> {
+/// Returns the inputs spending the given contract UTXOs, their total value,
+/// and the total amount of each token they hold.
+fn coin_picker<T: Config>(
+    outpoints: &Vec<H256>,
+) -> Result<(Vec<TransactionInput>, Value, BTreeMap<TokenId, Value>), DispatchError> {
     let mut inputs: Vec<TransactionInput> = Vec::new();
+    let mut total: Value = 0;
+    let mut tokens: BTreeMap<TokenId, Value> = BTreeMap::new();
 
     // consensus-critical sorting function...
     let mut outpoints = outpoints.clone();
@@ -1381,12 +2013,14 @@ fn coin_picker<T: Config>(outpoints: &Vec<H256>) -> Result<Vec<TransactionInput>
     for outpoint in outpoints.iter() {
         let tx = <UtxoStore<T>>::get(&outpoint).ok_or("UTXO doesn't exist!")?;
         match tx.destination {
-            Destination::CallPP(_, _, _) => {
-                inputs.push(TransactionInput::new_script(
-                    *outpoint,
-                    Builder::new().into_script(),
-                    Builder::new().push_int(0x1337).into_script(),
-                ));
+            Destination::CallPP(_, _, _, _) => {
+                // the contract spending its utxos is checked in the validation
+                inputs.push(TransactionInput::new_empty(*outpoint));
+                total = total.checked_add(tx.value).ok_or("input value overflow")?;
+                if let Some((token_id, amount)) = tx.token_transfer() {
+                    let token_total = tokens.entry(token_id).or_insert(0);
+                    *token_total = token_total.checked_add(amount).ok_or("input value overflow")?;
+                }
             }
             _ => {
                 return Err(DispatchError::Other("Only CallPP vouts can be spent!"));
@@ -1394,7 +2028,87 @@ fn coin_picker<T: Config>(outpoints: &Vec<H256>) -> Result<Vec<TransactionInput>
         }
     }
 
-    Ok(inputs)
+    Ok((inputs, total, tokens))
+}
+
+/// Spends the given UTXOs of the `caller` contract to the `outputs`.
+/// What is left of them goes back to the contract as change, in a `CallPP` output
+/// without input data, and one more for each token they hold.
+/// Returns the gas consumed by the contracts called by the `outputs`,
+/// and the outpoints of the new utxos.
+pub(crate) fn spend_contract_utxos<T: Config>(
+    caller: &T::AccountId,
+    outpoints: &Vec<H256>,
+    mut outputs: Vec<TransactionOutputFor<T>>,
+) -> Result<(Weight, Vec<H256>), DispatchError> {
+    let (inputs, total, mut tokens) = coin_picker::<T>(outpoints)?;
+    let spent = outputs
+        .iter()
+        .try_fold(0, |sum: Value, output| sum.checked_add(output.value))
+        .ok_or("output value overflow")?;
+    let change = total.checked_sub(spent).ok_or("Contract doesn't have enough funds")?;
+    if change > 0 {
+        outputs.push(TransactionOutput::new_call_pp(
+            change,
+            caller.clone(),
+            true,
+            Vec::new(),
+            0,
+        ));
+    }
+
+    // tokens left out of the outputs would be burned, so all of them have to go somewhere
+    for (token_id, amount) in outputs.iter().filter_map(|output| output.token_transfer()) {
+        let token_total = tokens.get_mut(&token_id).ok_or("Contract doesn't have enough tokens")?;
+        *token_total =
+            token_total.checked_sub(amount).ok_or("Contract doesn't have enough tokens")?;
+    }
+    for (token_id, change) in tokens {
+        if change > 0 {
+            outputs.push(TransactionOutput::new_call_pp_with_data(
+                0,
+                caller.clone(),
+                OutputData::TokenTransferV1 {
+                    token_id,
+                    amount: change,
+                },
+            ));
+        }
+    }
+
+    let tx = Transaction {
+        inputs,
+        outputs,
+        time_lock: Default::default(),
+    };
+    let gas_consumed =
+        spend_as_contract::<T>(caller, &tx).map_err(|_| "Failed to spend the transaction!")?;
+    let new_utxos = (0..tx.outputs.len()).map(|index| tx.outpoint(index as u64)).collect();
+    Ok((gas_consumed, new_utxos))
+}
+
+/// Stores a `CallPP` UTXO paying `value` to the `destination` contract without
+/// spending anything, and starts recording the transactions made by contracts.
+/// The UTXO is paid out of nothing, so this is only called by `dry_run`,
+/// which rolls everything back.
+fn dry_run_utxo<T: Config>(
+    destination: &T::AccountId,
+    value: u128,
+    fund_contract: bool,
+    data: &Vec<u8>,
+    gas_limit: Weight,
+) -> H256 {
+    let utxo = TransactionOutput::new_call_pp(
+        value,
+        destination.clone(),
+        fund_contract,
+        data.clone(),
+        gas_limit,
+    );
+    let hash = BlakeTwo256::hash_of(&(&utxo, "dry_run"));
+    UtxoStore::<T>::insert(hash, utxo);
+    <DryRunTransactions<T>>::put(Vec::<TransactionFor<T>>::new());
+    hash
 }
 
 impl<T: Config> UtxoApi for Pallet<T>
@@ -1435,20 +2149,16 @@ where
         dest: &T::AccountId,
         value: u128,
         outpoints: &Vec<H256>,
-    ) -> Result<(), DispatchError> {
+    ) -> Result<Vec<H256>, DispatchError> {
         let pubkey_raw: [u8; 32] =
             dest.encode().try_into().map_err(|_| "Failed to get caller's public key")?;
 
-        spend::<T>(
+        let (_, new_utxos) = spend_contract_utxos::<T>(
             caller,
-            &Transaction {
-                inputs: coin_picker::<T>(outpoints)?,
-                outputs: vec![TransactionOutput::new_pubkey(value, H256::from(pubkey_raw))],
-                time_lock: Default::default(),
-            },
-        )
-        .map_err(|_| "Failed to spend the transaction!")?;
-        Ok(())
+            outpoints,
+            vec![TransactionOutput::new_pubkey(value, H256::from(pubkey_raw))],
+        )?;
+        Ok(new_utxos)
     }
 
     fn send_conscrit_c2c(
@@ -1457,21 +2167,136 @@ where
         value: u128,
         data: &Vec<u8>,
         outpoints: &Vec<H256>,
-    ) -> Result<(), DispatchError> {
-        spend::<T>(
+        gas_limit: Weight,
+    ) -> Result<(Weight, Vec<H256>), DispatchError> {
+        spend_contract_utxos::<T>(
             caller,
-            &Transaction {
-                inputs: coin_picker::<T>(outpoints)?,
-                outputs: vec![TransactionOutput::new_call_pp(
-                    value,
-                    dest.clone(),
-                    true,
-                    data.clone(),
-                )],
-                time_lock: Default::default(),
-            },
+            outpoints,
+            vec![TransactionOutput::new_call_pp(
+                value,
+                dest.clone(),
+                true,
+                data.clone(),
+                gas_limit,
+            )],
         )
-        .map_err(|_| "Failed to spend the transaction!")?;
-        Ok(())
+    }
+
+    fn send_conscrit_token_p2pk(
+        caller: &T::AccountId,
+        dest: &T::AccountId,
+        token_id: &H160,
+        amount: u128,
+        outpoints: &Vec<H256>,
+    ) -> Result<Vec<H256>, DispatchError> {
+        let pubkey_raw: [u8; 32] =
+            dest.encode().try_into().map_err(|_| "Failed to get caller's public key")?;
+
+        let (_, new_utxos) = spend_contract_utxos::<T>(
+            caller,
+            outpoints,
+            vec![TransactionOutput::new_p2pk_with_data(
+                0,
+                H256::from(pubkey_raw),
+                OutputData::TokenTransferV1 {
+                    token_id: TokenId::from(*token_id),
+                    amount,
+                },
+            )],
+        )?;
+        Ok(new_utxos)
+    }
+
+    fn send_conscrit_token_c2c(
+        caller: &T::AccountId,
+        dest: &T::AccountId,
+        token_id: &H160,
+        amount: u128,
+        outpoints: &Vec<H256>,
+    ) -> Result<Vec<H256>, DispatchError> {
+        let (_, new_utxos) = spend_contract_utxos::<T>(
+            caller,
+            outpoints,
+            vec![TransactionOutput::new_call_pp_with_data(
+                0,
+                dest.clone(),
+                OutputData::TokenTransferV1 {
+                    token_id: TokenId::from(*token_id),
+                    amount,
+                },
+            )],
+        )?;
+        Ok(new_utxos)
+    }
+
+    fn send_conscrit_sweep(
+        caller: &T::AccountId,
+        beneficiary: &Beneficiary,
+        outpoints: &Vec<H256>,
+    ) -> Result<Vec<H256>, DispatchError> {
+        let destination = match beneficiary {
+            Beneficiary::Pubkey(pubkey) => {
+                Destination::Pubkey(sp_core::sr25519::Public::from_h256(*pubkey))
+            }
+            Beneficiary::ScriptHash(hash) => Destination::ScriptHash(*hash),
+        };
+
+        // everything goes to the beneficiary, so there is no change left for the contract
+        let (_, total, tokens) = coin_picker::<T>(outpoints)?;
+        let mut outputs = Vec::new();
+        if total > 0 {
+            outputs.push(TransactionOutput {
+                value: total,
+                destination: destination.clone(),
+                data: None,
+            });
+        }
+        for (token_id, amount) in tokens {
+            outputs.push(TransactionOutput {
+                value: 0,
+                destination: destination.clone(),
+                data: Some(OutputData::TokenTransferV1 { token_id, amount }),
+            });
+        }
+
+        let (_, new_utxos) = spend_contract_utxos::<T>(caller, outpoints, outputs)?;
+        Ok(new_utxos)
+    }
+
+    fn utxo(outpoint: &H256) -> Option<Vec<u8>> {
+        UtxoStore::<T>::get(outpoint).map(|utxo| utxo.encode())
+    }
+
+    fn token_issuance(token_id: &H160) -> Option<Vec<u8>> {
+        let tx = TokenIssuanceTransactions::<T>::get(TokenId::from(*token_id))?;
+        tx.outputs
+            .iter()
+            .filter_map(|output| output.data.as_ref())
+            .find(|data| matches!(data, OutputData::TokenIssuanceV1 { .. }))
+            .map(|data| data.encode())
+    }
+
+    fn timestamp() -> u64 {
+        <pallet_timestamp::Pallet<T> as UnixTime>::now().as_secs()
+    }
+
+    fn dry_run<R>(
+        destination: &T::AccountId,
+        value: u128,
+        fund_contract: bool,
+        data: &Vec<u8>,
+        gas_limit: Weight,
+        call: impl FnOnce(H256) -> R,
+    ) -> (R, Vec<Vec<u8>>) {
+        with_transaction(|| {
+            let utxo_hash = dry_run_utxo::<T>(destination, value, fund_contract, data, gas_limit);
+            let result = call(utxo_hash);
+            let transactions = <DryRunTransactions<T>>::get()
+                .unwrap_or_default()
+                .iter()
+                .map(|tx| tx.encode())
+                .collect();
+            TransactionOutcome::Rollback((result, transactions))
+        })
     }
 }
@@ -0,0 +1,224 @@
+// Copyright (c) 2021 RBB S.r.l
+// opensource@mintlayer.org
+// SPDX-License-Identifier: MIT
+// Licensed under the MIT License;
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// 	http://spdx.org/licenses/MIT
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::{
+    script::RawBlockTime,
+    tokens::{OutputData, Value},
+    Config, Destination, LockedUtxos, Pallet, TokenIssuanceTransactions, Transaction,
+    TransactionInput, TransactionOutput, UtxoStore, ValidatorPreferences, STORAGE_VERSION,
+};
+#[cfg(any(test, feature = "try-runtime"))]
+use codec::DecodeAll;
+use codec::{Decode, Encode};
+use frame_support::{
+    dispatch::Vec,
+    traits::{Get, GetStorageVersion},
+    weights::Weight,
+};
+#[cfg(any(test, feature = "try-runtime"))]
+use frame_support::{ensure, storage::unhashed};
+use sp_core::{sr25519, H256};
+
+/// The layout of the stored outputs, before the storage version 1.
+pub mod v0 {
+    use super::*;
+
+    #[derive(Encode, Decode)]
+    pub enum Destination<AccountId> {
+        Pubkey(sr25519::Public),
+        /// without the gas limit.
+        CreatePP(Vec<u8>, Vec<u8>),
+        /// without the gas limit.
+        CallPP(AccountId, bool, Vec<u8>),
+        ScriptHash(H256),
+        /// without the validator preferences.
+        LockForStaking {
+            stash_account: AccountId,
+            controller_account: AccountId,
+            session_key: Vec<u8>,
+        },
+        LockExtraForStaking {
+            stash_account: AccountId,
+            controller_account: AccountId,
+        },
+    }
+
+    #[derive(Encode, Decode)]
+    pub struct TransactionOutput<AccountId> {
+        pub value: Value,
+        pub destination: Destination<AccountId>,
+        pub data: Option<OutputData>,
+    }
+
+    #[derive(Encode, Decode)]
+    pub struct Transaction<AccountId> {
+        pub inputs: Vec<TransactionInput>,
+        pub outputs: Vec<TransactionOutput<AccountId>>,
+        pub time_lock: RawBlockTime,
+    }
+}
+
+impl<AccountId> From<v0::Destination<AccountId>> for Destination<AccountId> {
+    fn from(old: v0::Destination<AccountId>) -> Self {
+        match old {
+            v0::Destination::Pubkey(pubkey) => Destination::Pubkey(pubkey),
+            // the contracts of the stored outputs were already instantiated or called.
+            v0::Destination::CreatePP(code, data) => Destination::CreatePP(code, data, 0),
+            v0::Destination::CallPP(dest, fund, data) => Destination::CallPP(dest, fund, data, 0),
+            v0::Destination::ScriptHash(hash) => Destination::ScriptHash(hash),
+            v0::Destination::LockForStaking {
+                stash_account,
+                controller_account,
+                session_key,
+            } => Destination::LockForStaking {
+                stash_account,
+                controller_account,
+                session_key,
+                // the same as the defaults of `pallet-staking`, used until now.
+                validator_prefs: ValidatorPreferences::default(),
+            },
+            v0::Destination::LockExtraForStaking {
+                stash_account,
+                controller_account,
+            } => Destination::LockExtraForStaking {
+                stash_account,
+                controller_account,
+            },
+        }
+    }
+}
+
+impl<AccountId> From<v0::TransactionOutput<AccountId>> for TransactionOutput<AccountId> {
+    fn from(old: v0::TransactionOutput<AccountId>) -> Self {
+        TransactionOutput {
+            value: old.value,
+            destination: old.destination.into(),
+            data: old.data,
+        }
+    }
+}
+
+impl<AccountId> From<v0::Transaction<AccountId>> for Transaction<AccountId> {
+    fn from(old: v0::Transaction<AccountId>) -> Self {
+        Transaction {
+            inputs: old.inputs,
+            outputs: old.outputs.into_iter().map(Into::into).collect(),
+            time_lock: old.time_lock,
+        }
+    }
+}
+
+/// Translates the stored outputs and transactions to the layout of the storage version 1,
+/// where `CreatePP` and `CallPP` carry a gas limit, and `LockForStaking` the validator preferences.
+pub fn migrate_to_v1<T: Config>() -> Weight {
+    let on_chain_version = Pallet::<T>::on_chain_storage_version();
+    if on_chain_version >= 1 {
+        log::info!(
+            "pallet-utxo storage is already at version {:?}, skipping the migration.",
+            on_chain_version
+        );
+        return T::DbWeight::get().reads(1);
+    }
+
+    let mut translated: Weight = 0;
+    <UtxoStore<T>>::translate::<v0::TransactionOutput<T::AccountId>, _>(|_, old| {
+        translated += 1;
+        Some(old.into())
+    });
+    <LockedUtxos<T>>::translate::<v0::TransactionOutput<T::AccountId>, _>(|_, old| {
+        translated += 1;
+        Some(old.into())
+    });
+    <TokenIssuanceTransactions<T>>::translate::<v0::Transaction<T::AccountId>, _>(|_, old| {
+        translated += 1;
+        Some(old.into())
+    });
+
+    STORAGE_VERSION.put::<Pallet<T>>();
+    log::info!(
+        "pallet-utxo storage migrated to version {:?}: {} entries translated.",
+        STORAGE_VERSION,
+        translated
+    );
+
+    T::DbWeight::get().reads_writes(translated + 1, translated + 1)
+}
+
+/// Whether the value stored at each of the `keys` decodes as a whole to a `V`.
+#[cfg(any(test, feature = "try-runtime"))]
+fn all_decode_as<V: Decode>(mut keys: impl Iterator<Item = Vec<u8>>) -> bool {
+    keys.all(|key| unhashed::get_raw(&key).map_or(false, |raw| V::decode_all(&raw).is_ok()))
+}
+
+/// Checks, before the upgrade, that the stored outputs and transactions are all in
+/// the layout of the storage version 0 that `migrate_to_v1` translates from.
+#[cfg(any(test, feature = "try-runtime"))]
+pub fn pre_migrate_to_v1<T: Config>() -> Result<(), &'static str> {
+    if Pallet::<T>::on_chain_storage_version() >= 1 {
+        return Ok(());
+    }
+
+    ensure!(
+        all_decode_as::<v0::TransactionOutput<T::AccountId>>(
+            <UtxoStore<T>>::iter_keys().map(<UtxoStore<T>>::hashed_key_for)
+        ),
+        "UtxoStore has outputs that are not in the v0 layout"
+    );
+    ensure!(
+        all_decode_as::<v0::TransactionOutput<T::AccountId>>(
+            <LockedUtxos<T>>::iter_keys().map(<LockedUtxos<T>>::hashed_key_for)
+        ),
+        "LockedUtxos has outputs that are not in the v0 layout"
+    );
+    ensure!(
+        all_decode_as::<v0::Transaction<T::AccountId>>(
+            <TokenIssuanceTransactions<T>>::iter_keys()
+                .map(<TokenIssuanceTransactions<T>>::hashed_key_for)
+        ),
+        "TokenIssuanceTransactions has transactions that are not in the v0 layout"
+    );
+    Ok(())
+}
+
+/// Checks, after the upgrade, that the storage version is 1 and that
+/// the stored outputs and transactions are all in its layout.
+#[cfg(any(test, feature = "try-runtime"))]
+pub fn post_migrate_to_v1<T: Config>() -> Result<(), &'static str> {
+    ensure!(
+        Pallet::<T>::on_chain_storage_version() == STORAGE_VERSION,
+        "pallet-utxo storage was not migrated to version 1"
+    );
+
+    ensure!(
+        all_decode_as::<TransactionOutput<T::AccountId>>(
+            <UtxoStore<T>>::iter_keys().map(<UtxoStore<T>>::hashed_key_for)
+        ),
+        "UtxoStore has outputs that were not migrated"
+    );
+    ensure!(
+        all_decode_as::<TransactionOutput<T::AccountId>>(
+            <LockedUtxos<T>>::iter_keys().map(<LockedUtxos<T>>::hashed_key_for)
+        ),
+        "LockedUtxos has outputs that were not migrated"
+    );
+    ensure!(
+        all_decode_as::<Transaction<T::AccountId>>(
+            <TokenIssuanceTransactions<T>>::iter_keys()
+                .map(<TokenIssuanceTransactions<T>>::hashed_key_for)
+        ),
+        "TokenIssuanceTransactions has transactions that were not migrated"
+    );
+    Ok(())
+}
@@ -15,7 +15,7 @@
 //
 // Author(s): C. Yap
 use crate as pallet_utxo;
-use pallet_utxo::staking::StakingHelper;
+use pallet_utxo::staking::{StakingHelper, ValidatorPreferences};
 use pallet_utxo::TransactionOutput;
 use pp_api::ProgrammablePoolApi;
 
@@ -24,21 +24,26 @@ use crate::MLT_UNIT;
 use frame_support::dispatch::DispatchResultWithPostInfo;
 use frame_support::{dispatch::Vec, weights::Weight};
 use frame_support::{
-    parameter_types,
+    ensure, parameter_types,
     sp_io::TestExternalities,
     sp_runtime::{
         testing::Header,
         traits::{BlakeTwo256, Hash, IdentityLookup},
-        Percent,
+        DispatchError, Percent,
     },
     traits::GenesisBuild,
 };
 use frame_system::Config as SysConfig;
 use sp_core::{
-    sp_std::{cell::RefCell, collections::btree_map::BTreeMap, marker::PhantomData, vec},
+    sp_std::{
+        cell::RefCell,
+        collections::{btree_map::BTreeMap, btree_set::BTreeSet},
+        marker::PhantomData,
+        vec,
+    },
     sr25519::Public,
     testing::SR25519,
-    H256,
+    H160, H256,
 };
 use sp_keystore::{testing::KeyStore, KeystoreExt, SyncCryptoStore};
 
@@ -52,14 +57,25 @@ pub struct MockStaking<T: pallet_utxo::Config> {
     pub withdrawal_span: T::BlockNumber,
     pub current_block: T::BlockNumber,
     pub lock_map: BTreeMap<T::AccountId, Option<T::BlockNumber>>,
+    /// the stashes whose unlocking leaves part of the stake active.
+    pub partial_unlocks: BTreeSet<T::AccountId>,
     pub ctrl_map: BTreeMap<T::AccountId, T::AccountId>,
     pub lock_ctrl_map: BTreeMap<T::AccountId, T::AccountId>,
+    pub nominations: BTreeMap<T::AccountId, Vec<T::AccountId>>,
+    pub era_rewards: BTreeMap<(T::AccountId, u32), Vec<(T::AccountId, Value)>>,
     pub marker: PhantomData<T>,
 }
 
 thread_local! {
     pub static AUTHORITIES: RefCell<Vec<Public>> = RefCell::new(vec![]);
     pub static MOCK_STAKING: RefCell<MockStaking<Test>> = RefCell::new(MockStaking::new());
+    // the funds and utxos of each contract, as `pallet-pp` keeps them.
+    pub static MOCK_CONTRACTS: RefCell<BTreeMap<AccountId, (u128, Vec<(H256, u128)>)>> =
+        RefCell::new(BTreeMap::new());
+    // the funds and utxos of each contract in each token.
+    pub static MOCK_CONTRACT_TOKENS:
+        RefCell<BTreeMap<(AccountId, H160), (u128, Vec<(H256, u128)>)>> =
+        RefCell::new(BTreeMap::new());
 }
 
 pub const ALICE_PHRASE: &str =
@@ -83,28 +99,112 @@ pub struct MockPool<T>(PhantomData<T>);
 impl<T: SysConfig> ProgrammablePoolApi for MockPool<T> {
     type AccountId = AccountId;
 
+    // consumes half of the gas limit.
     fn create(
         _origin: &Self::AccountId,
-        _weight: Weight,
-        _code: &Vec<u8>,
-        _utxo_hash: H256,
-        _utxo_value: u128,
+        weight: Weight,
+        code: &Vec<u8>,
+        utxo_hash: H256,
+        utxo_value: u128,
         _data: &Vec<u8>,
-    ) -> Result<(), &'static str> {
-        Ok(())
+    ) -> Result<(Self::AccountId, Weight), &'static str> {
+        ensure!(!code.is_empty(), "Failed to instantiate smart contract");
+        let contract = BlakeTwo256::hash_of(&(code, utxo_hash));
+        MOCK_CONTRACTS.with(|contracts| {
+            contracts
+                .borrow_mut()
+                .insert(contract, (utxo_value, vec![(utxo_hash, utxo_value)]));
+        });
+        Ok((contract, weight / 2))
     }
 
+    // consumes half of the gas limit, and reverts if the input data starts with 0xff.
     fn call(
         _caller: &Self::AccountId,
-        _dest: &Self::AccountId,
-        _gas_limit: Weight,
-        _utxo_hash: H256,
-        _utxo_value: u128,
-        _fund_contract: bool,
-        _input_data: &Vec<u8>,
+        dest: &Self::AccountId,
+        gas_limit: Weight,
+        utxo_hash: H256,
+        utxo_value: u128,
+        utxo_token: Option<(H160, u128)>,
+        fund_contract: bool,
+        input_data: &Vec<u8>,
+    ) -> Result<Weight, &'static str> {
+        ensure!(
+            input_data.first() != Some(&0xff),
+            "Smart contract call reverted"
+        );
+        MOCK_CONTRACTS.with(|contracts| {
+            let mut contracts = contracts.borrow_mut();
+            let (funds, utxos) = contracts.entry(*dest).or_insert((0, vec![]));
+            utxos.push((utxo_hash, utxo_value));
+            if fund_contract {
+                *funds += utxo_value;
+            }
+        });
+        if let Some((token_id, amount)) = utxo_token {
+            MOCK_CONTRACT_TOKENS.with(|tokens| {
+                let mut tokens = tokens.borrow_mut();
+                let (funds, utxos) = tokens.entry((*dest, token_id)).or_insert((0, vec![]));
+                utxos.push((utxo_hash, amount));
+                if fund_contract {
+                    *funds += amount;
+                }
+            });
+        }
+
+        // without input data, the contract is only funded
+        if input_data.is_empty() {
+            return Ok(0);
+        }
+        Ok(gas_limit / 2)
+    }
+
+    fn on_utxo_spent(
+        contract: &Self::AccountId,
+        utxo_hash: H256,
+        utxo_value: u128,
+        utxo_token: Option<(H160, u128)>,
+        fund_contract: bool,
     ) -> Result<(), &'static str> {
+        MOCK_CONTRACTS.with(|contracts| -> Result<(), &'static str> {
+            let mut contracts = contracts.borrow_mut();
+            let (funds, utxos) = contracts.get_mut(contract).ok_or("Contract doesn't exist!")?;
+            let index = utxos
+                .iter()
+                .position(|(hash, _)| *hash == utxo_hash)
+                .ok_or("Contract doesn't own the UTXO")?;
+            utxos.remove(index);
+            if fund_contract {
+                *funds = funds.checked_sub(utxo_value).ok_or("Contract funds underflow")?;
+            }
+            Ok(())
+        })?;
+        if let Some((token_id, amount)) = utxo_token {
+            MOCK_CONTRACT_TOKENS.with(|tokens| -> Result<(), &'static str> {
+                let mut tokens = tokens.borrow_mut();
+                let (funds, utxos) = tokens
+                    .get_mut(&(*contract, token_id))
+                    .ok_or("Contract doesn't own the token")?;
+                utxos.retain(|(hash, _)| *hash != utxo_hash);
+                if fund_contract {
+                    *funds = funds.checked_sub(amount).ok_or("Contract funds underflow")?;
+                }
+                Ok(())
+            })?;
+        }
         Ok(())
     }
+
+    fn contract_balance(contract: &Self::AccountId) -> Option<(u128, Vec<(H256, u128)>)> {
+        MOCK_CONTRACTS.with(|contracts| contracts.borrow().get(contract).cloned())
+    }
+
+    fn contract_token_balance(
+        contract: &Self::AccountId,
+        token_id: &H160,
+    ) -> Option<(u128, Vec<(H256, u128)>)> {
+        MOCK_CONTRACT_TOKENS.with(|tokens| tokens.borrow().get(&(*contract, *token_id)).cloned())
+    }
 }
 
 impl MockStaking<Test> {
@@ -113,11 +213,19 @@ impl MockStaking<Test> {
             withdrawal_span: 5,
             current_block: 0,
             lock_map: BTreeMap::new(),
+            partial_unlocks: BTreeSet::new(),
             ctrl_map: BTreeMap::new(),
             lock_ctrl_map: BTreeMap::new(),
+            nominations: BTreeMap::new(),
+            era_rewards: BTreeMap::new(),
             marker: Default::default(),
         }
     }
+
+    /// a nominator is its own controller, so only the controllers of other stashes count.
+    fn is_controller_of_another(&self, account: &AccountId) -> bool {
+        matches!(self.ctrl_map.get(account), Some(stash) if stash != account)
+    }
 }
 
 pub fn next_block() {
@@ -127,6 +235,14 @@ pub fn next_block() {
     })
 }
 
+/// sets the rewards of the validator and its nominators, for the given era.
+pub fn set_era_rewards(validator_stash: AccountId, era: u32, rewards: Vec<(AccountId, Value)>) {
+    MOCK_STAKING.with(|stake_info| {
+        let mut stake_info = stake_info.borrow_mut();
+        stake_info.era_rewards.insert((validator_stash, era), rewards);
+    })
+}
+
 impl<T: pallet_utxo::Config> StakingHelper<AccountId> for MockStaking<T> {
     fn get_controller_account(stash_account: &AccountId) -> Result<AccountId, &'static str> {
         MOCK_STAKING.with(|stake_info| {
@@ -157,6 +273,7 @@ impl<T: pallet_utxo::Config> StakingHelper<AccountId> for MockStaking<T> {
 
             if let Some(stash) = stake_info.ctrl_map.get(controller_account) {
                 if let Some(Some(_)) = stake_info.lock_map.get(stash) {
+                    return stake_info.partial_unlocks.contains(stash);
                 } else {
                     return true;
                 }
@@ -165,6 +282,17 @@ impl<T: pallet_utxo::Config> StakingHelper<AccountId> for MockStaking<T> {
         })
     }
 
+    fn has_unlocking_funds(controller_account: &AccountId) -> bool {
+        MOCK_STAKING.with(|stake_info| {
+            let stake_info = stake_info.borrow();
+
+            match stake_info.ctrl_map.get(controller_account) {
+                Some(stash) => matches!(stake_info.lock_map.get(stash), Some(Some(_))),
+                None => false,
+            }
+        })
+    }
+
     fn check_accounts_matched(controller_account: &AccountId, stash_account: &AccountId) -> bool {
         MOCK_STAKING.with(|stake_info| {
             let stake_info = stake_info.borrow();
@@ -183,6 +311,7 @@ impl<T: pallet_utxo::Config> StakingHelper<AccountId> for MockStaking<T> {
         controller_account: &AccountId,
         _rotate_keys: &Vec<u8>,
         _value: u128,
+        _validator_prefs: &ValidatorPreferences,
     ) -> DispatchResultWithPostInfo {
         MOCK_STAKING.with(|stake_info| {
             let mut stake_info = stake_info.borrow_mut();
@@ -220,7 +349,7 @@ impl<T: pallet_utxo::Config> StakingHelper<AccountId> for MockStaking<T> {
                 Err(pallet_utxo::Error::<T>::StashAccountNotFound)?
             }
 
-            if stake_info.ctrl_map.contains_key(stash_account) {
+            if stake_info.is_controller_of_another(stash_account) {
                 Err("CANNOT STAKE. STASH ACCOUNT IS ACTUALLY A CONTROLLER ACCOUNT")?
             }
 
@@ -228,11 +357,129 @@ impl<T: pallet_utxo::Config> StakingHelper<AccountId> for MockStaking<T> {
         })
     }
 
-    fn unlock_request_for_withdrawal(stash_account: &AccountId) -> DispatchResultWithPostInfo {
+    fn set_validator_preferences(
+        stash_account: &AccountId,
+        _validator_prefs: &ValidatorPreferences,
+    ) -> DispatchResultWithPostInfo {
+        MOCK_STAKING.with(|stake_info| {
+            let stake_info = stake_info.borrow();
+
+            if !stake_info.lock_map.contains_key(stash_account) {
+                Err(pallet_utxo::Error::<T>::StashAccountNotFound)?
+            }
+
+            Ok(().into())
+        })
+    }
+
+    fn rotate_session_keys(
+        stash_account: &AccountId,
+        _session_key: &Vec<u8>,
+    ) -> DispatchResultWithPostInfo {
+        MOCK_STAKING.with(|stake_info| {
+            let stake_info = stake_info.borrow();
+
+            if !stake_info.lock_map.contains_key(stash_account) {
+                Err(pallet_utxo::Error::<T>::StashAccountNotFound)?
+            }
+
+            Ok(().into())
+        })
+    }
+
+    fn change_controller(
+        stash_account: &AccountId,
+        controller_account: &AccountId,
+    ) -> DispatchResultWithPostInfo {
+        MOCK_STAKING.with(|stake_info| {
+            let mut stake_info = stake_info.borrow_mut();
+
+            if stake_info.ctrl_map.contains_key(controller_account) {
+                Err("CANNOT CHANGE. CONTROLLER ACCOUNT IS ALREADY USED.")?
+            }
+
+            let old_controller = stake_info
+                .lock_ctrl_map
+                .insert(stash_account.clone(), controller_account.clone())
+                .ok_or(pallet_utxo::Error::<T>::StashAccountNotFound)?;
+            stake_info.ctrl_map.remove(&old_controller);
+            stake_info.ctrl_map.insert(controller_account.clone(), stash_account.clone());
+
+            Ok(().into())
+        })
+    }
+
+    fn are_valid_nomination_targets(targets: &Vec<AccountId>) -> bool {
+        MOCK_STAKING.with(|stake_info| {
+            let stake_info = stake_info.borrow();
+
+            targets.len() <= MaxNominations::get() as usize
+                && targets.iter().all(|target| {
+                    stake_info.lock_map.contains_key(target)
+                        && !stake_info.nominations.contains_key(target)
+                })
+        })
+    }
+
+    fn lock_for_nominating(
+        stash_account: &AccountId,
+        targets: &Vec<AccountId>,
+        _value: u128,
+    ) -> DispatchResultWithPostInfo {
         MOCK_STAKING.with(|stake_info| {
             let mut stake_info = stake_info.borrow_mut();
 
+            if stake_info.lock_map.contains_key(stash_account) {
+                Err("CANNOT NOMINATE. STASH ACCOUNT IS ALREADY REGISTERED.")?
+            }
+
             if stake_info.ctrl_map.contains_key(stash_account) {
+                Err("CANNOT NOMINATE. STASH ACCOUNT IS ACTUALLY A CONTROLLER ACCOUNT")?
+            }
+
+            stake_info.lock_map.insert(stash_account.clone(), None);
+            stake_info.lock_ctrl_map.insert(stash_account.clone(), stash_account.clone());
+            stake_info.ctrl_map.insert(stash_account.clone(), stash_account.clone());
+            stake_info.nominations.insert(stash_account.clone(), targets.clone());
+
+            Ok(().into())
+        })
+    }
+
+    fn nominate(stash_account: &AccountId, targets: &Vec<AccountId>) -> DispatchResultWithPostInfo {
+        MOCK_STAKING.with(|stake_info| {
+            let mut stake_info = stake_info.borrow_mut();
+
+            if !stake_info.nominations.contains_key(stash_account) {
+                Err("CANNOT NOMINATE. STASH ACCOUNT IS NOT A NOMINATOR.")?
+            }
+
+            stake_info.nominations.insert(stash_account.clone(), targets.clone());
+            Ok(().into())
+        })
+    }
+
+    fn rebond(stash_account: &AccountId) -> DispatchResultWithPostInfo {
+        MOCK_STAKING.with(|stake_info| {
+            let mut stake_info = stake_info.borrow_mut();
+
+            match stake_info.lock_map.get(stash_account) {
+                Some(Some(_)) => {
+                    stake_info.lock_map.insert(stash_account.clone(), None);
+                    stake_info.partial_unlocks.remove(stash_account);
+                    Ok(().into())
+                }
+                Some(None) => Err("NoUnlockChunk".into()),
+                None => Err(pallet_utxo::Error::<T>::StashAccountNotFound.into()),
+            }
+        })
+    }
+
+    fn unlock_request_for_withdrawal(stash_account: &AccountId) -> DispatchResultWithPostInfo {
+        MOCK_STAKING.with(|stake_info| {
+            let mut stake_info = stake_info.borrow_mut();
+
+            if stake_info.is_controller_of_another(stash_account) {
                 Err("CANNOT PAUSE. STASH ACCOUNT IS ACTUALLY A CONTROLLER ACCOUNT")?
             }
 
@@ -252,6 +499,17 @@ impl<T: pallet_utxo::Config> StakingHelper<AccountId> for MockStaking<T> {
         })
     }
 
+    fn partial_unlock_request_for_withdrawal(
+        stash_account: &AccountId,
+        _value: u128,
+    ) -> DispatchResultWithPostInfo {
+        let res = Self::unlock_request_for_withdrawal(stash_account)?;
+        MOCK_STAKING.with(|stake_info| {
+            stake_info.borrow_mut().partial_unlocks.insert(stash_account.clone());
+        });
+        Ok(res)
+    }
+
     fn withdraw(stash_account: &AccountId) -> DispatchResultWithPostInfo {
         MOCK_STAKING.with(|stake_info| {
             let mut stake_info = stake_info.borrow_mut();
@@ -262,7 +520,28 @@ impl<T: pallet_utxo::Config> StakingHelper<AccountId> for MockStaking<T> {
                         let ctrl_account = stake_info.lock_ctrl_map.remove(stash_account).unwrap();
                         stake_info.ctrl_map.remove(&ctrl_account);
                         stake_info.lock_map.remove(&stash_account);
+                        stake_info.nominations.remove(&stash_account);
+
+                        Ok(().into())
+                    } else {
+                        Err("not yet time to withdraw".into())
+                    }
+                }
+                Some(_) | None => Err("not yet unlocked".into()),
+            }
+        })
+    }
+
+    fn withdraw_partial(stash_account: &AccountId) -> DispatchResultWithPostInfo {
+        MOCK_STAKING.with(|stake_info| {
+            let mut stake_info = stake_info.borrow_mut();
 
+            match stake_info.lock_map.get(stash_account) {
+                Some(Some(withdrawal_block)) => {
+                    if *withdrawal_block <= stake_info.current_block {
+                        // the rest of the stake remains locked.
+                        stake_info.lock_map.insert(stash_account.clone(), None);
+                        stake_info.partial_unlocks.remove(stash_account);
                         Ok(().into())
                     } else {
                         Err("not yet time to withdraw".into())
@@ -272,6 +551,37 @@ impl<T: pallet_utxo::Config> StakingHelper<AccountId> for MockStaking<T> {
             }
         })
     }
+
+    fn remove_stake(stash_account: &AccountId) -> DispatchResultWithPostInfo {
+        MOCK_STAKING.with(|stake_info| {
+            let mut stake_info = stake_info.borrow_mut();
+
+            let ctrl_account = stake_info
+                .lock_ctrl_map
+                .remove(stash_account)
+                .ok_or(pallet_utxo::Error::<T>::StashAccountNotFound)?;
+            stake_info.ctrl_map.remove(&ctrl_account);
+            stake_info.lock_map.remove(stash_account);
+            stake_info.partial_unlocks.remove(stash_account);
+            stake_info.nominations.remove(stash_account);
+
+            Ok(().into())
+        })
+    }
+
+    fn payout_stakers(
+        validator_stash: &AccountId,
+        era: u32,
+    ) -> Result<Vec<(AccountId, Value)>, DispatchError> {
+        MOCK_STAKING.with(|stake_info| {
+            let mut stake_info = stake_info.borrow_mut();
+
+            stake_info
+                .era_rewards
+                .remove(&(validator_stash.clone(), era))
+                .ok_or(DispatchError::Other("AlreadyClaimed"))
+        })
+    }
 }
 
 // Configure a mock runtime to test the pallet.
@@ -333,11 +643,26 @@ impl pallet_timestamp::Config for Test {
 parameter_types! {
     pub const MaxAuthorities: u32 = 1000;
     pub const MinimumStake: u128 = 10;
+    pub const MinimumNominatorStake: u128 = 5;
+    pub const MaxNominations: u32 = 2;
     pub const InitialReward: u128 = 100;
     pub const DefaultMinimumReward: u128 = 1;
     pub const StakeWithdrawalFee: u128 = 1;
+    pub const MaxNominatorRewardedPerValidator: u32 = 64;
+    pub const GasPrice: u128 = 2;
     pub const RewardReductionPeriod: BlockNumber = 5;
     pub const RewardReductionFraction: Percent = Percent::from_percent(25);
+    pub const RewardMaturity: BlockNumber = 3;
+    pub BlockRewardDistribution: pallet_utxo::RewardDistribution = pallet_utxo::RewardDistribution {
+        author: Percent::from_percent(60),
+        stakers: Percent::from_percent(30),
+        treasury: H256::repeat_byte(1),
+    };
+    pub const UncleRewardFraction: Percent = Percent::from_percent(50);
+    pub const MaxUncleAge: BlockNumber = 2;
+    pub storage MissingAuthor: pallet_utxo::MissingAuthorPolicy =
+        pallet_utxo::MissingAuthorPolicy::CarryOver;
+    pub storage StakeSlashPolicy: pallet_utxo::SlashPolicy = pallet_utxo::SlashPolicy::Burn;
 }
 
 impl pallet_utxo::Config for Test {
@@ -345,8 +670,14 @@ impl pallet_utxo::Config for Test {
     type Call = Call;
     type WeightInfo = crate::weights::WeightInfo<Test>;
     type ProgrammablePool = MockPool<Test>;
+    type GasPrice = GasPrice;
     type RewardReductionFraction = RewardReductionFraction;
     type RewardReductionPeriod = RewardReductionPeriod;
+    type RewardMaturity = RewardMaturity;
+    type RewardDistribution = BlockRewardDistribution;
+    type UncleRewardFraction = UncleRewardFraction;
+    type MaxUncleAge = MaxUncleAge;
+    type MissingAuthorPolicy = MissingAuthor;
 
     fn authorities() -> Vec<H256> {
         AUTHORITIES.with(|auths| {
@@ -357,7 +688,10 @@ impl pallet_utxo::Config for Test {
 
     type StakingHelper = MockStaking<Test>;
     type MinimumStake = MinimumStake;
+    type MinimumNominatorStake = MinimumNominatorStake;
     type StakeWithdrawalFee = StakeWithdrawalFee;
+    type MaxNominatorRewardedPerValidator = MaxNominatorRewardedPerValidator;
+    type SlashPolicy = StakeSlashPolicy;
     type InitialReward = InitialReward;
     type DefaultMinimumReward = DefaultMinimumReward;
 }
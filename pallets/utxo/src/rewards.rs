@@ -16,16 +16,112 @@
 // Author(s): C. Yap
 
 use crate::{
-    convert_to_h256, tokens::Value, BlockAuthor, Config, Event, Pallet, RewardTotal,
-    TransactionOutput, UtxoStore,
+    convert_to_h256, supply, tokens::Value, BlockAuthor, Config, Event, Pallet, RewardSchedule,
+    RewardTotal, RewardUtxoHeight, StakingCount, TransactionOutput, TransactionOutputFor,
+    UncleAuthors, UtxoStore,
 };
 
-use frame_support::traits::Get;
-use sp_core::H256;
-use sp_runtime::traits::{BlakeTwo256, CheckedDiv, Hash, SaturatedConversion, Zero};
+use codec::{Decode, Encode};
+use frame_support::{dispatch::Vec, ensure, traits::Get, RuntimeDebug};
+#[cfg(feature = "std")]
+use serde::{Deserialize, Serialize};
+use sp_core::{sp_std::collections::btree_map::BTreeMap, H256};
+use sp_runtime::helpers_128bit::multiply_by_rational;
+use sp_runtime::traits::{
+    AtLeast32BitUnsigned, BlakeTwo256, CheckedDiv, Hash, SaturatedConversion, Zero,
+};
 use sp_runtime::Percent;
 use sp_std::convert::TryInto;
 
+/// How the reward of each block is divided.
+/// Whatever is left after the author's and the stakers' shares, goes to the treasury.
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
+#[derive(Clone, Encode, Decode, Eq, PartialEq, RuntimeDebug)]
+pub struct RewardDistribution {
+    /// the share of the block author.
+    pub author: Percent,
+    /// the share divided among everyone in the `StakingCount`, pro-rata to their stake.
+    pub stakers: Percent,
+    /// the script hash of the treasury.
+    pub treasury: H256,
+}
+
+/// Where the reward goes, when the block author could not be found.
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
+#[derive(Clone, Copy, Encode, Decode, Eq, PartialEq, RuntimeDebug)]
+pub enum MissingAuthorPolicy {
+    /// the reward and fees are added to the fees of the next block.
+    CarryOver,
+    /// the reward and fees go to the treasury of the `RewardDistribution`.
+    Treasury,
+}
+
+/// How the block reward changes over time.
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
+#[derive(Clone, Encode, Decode, Eq, PartialEq, RuntimeDebug)]
+pub enum EmissionSchedule<BlockNumber> {
+    /// a list of (start block, reward). Each reward is given from its start block,
+    /// until the start block of the next one.
+    Steps(Vec<(BlockNumber, Value)>),
+    /// the reward is reduced by `reduction_fraction` of the `initial_reward` every
+    /// `reduction_period`. Once reduced by 100%, it stays at the `minimum_reward`.
+    Reduction {
+        initial_reward: Value,
+        minimum_reward: Value,
+        reduction_fraction: Percent,
+        reduction_period: BlockNumber,
+    },
+}
+
+impl<BlockNumber: AtLeast32BitUnsigned + Copy> EmissionSchedule<BlockNumber> {
+    /// Checks whether the schedule can be used to compute the block rewards.
+    pub fn validate(&self) -> Result<(), &'static str> {
+        match self {
+            EmissionSchedule::Steps(steps) => {
+                ensure!(!steps.is_empty(), "emission schedule has no steps");
+                ensure!(
+                    steps.windows(2).all(|pair| pair[0].0 < pair[1].0),
+                    "emission schedule steps must be in ascending order of start block"
+                );
+            }
+            EmissionSchedule::Reduction {
+                initial_reward,
+                minimum_reward,
+                ..
+            } => {
+                ensure!(
+                    minimum_reward <= initial_reward,
+                    "minimum reward must not exceed the initial reward"
+                );
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns the block reward at the given block number.
+    pub fn reward_at(&self, block_number: BlockNumber) -> Value {
+        match self {
+            EmissionSchedule::Steps(steps) => steps
+                .iter()
+                .take_while(|(start, _)| *start <= block_number)
+                .last()
+                .map_or(0, |(_, reward)| *reward),
+            EmissionSchedule::Reduction {
+                initial_reward,
+                minimum_reward,
+                reduction_fraction,
+                reduction_period,
+            } => reduced_reward(
+                block_number,
+                *initial_reward,
+                *minimum_reward,
+                *reduction_fraction,
+                *reduction_period,
+            ),
+        }
+    }
+}
+
 /// handle event when a block author is found.
 impl<T> pallet_authorship::EventHandler<T::AccountId, T::BlockNumber> for Pallet<T>
 where
@@ -43,17 +139,33 @@ where
         }
     }
 
-    fn note_uncle(_author: T::AccountId, _age: T::BlockNumber) {
-        log::info!("TODO: no support for this. Or is there...?");
+    fn note_uncle(author: T::AccountId, age: T::BlockNumber) {
+        match convert_to_h256::<T>(&author) {
+            Ok(author_h256) => note_uncle_author::<T>(author_h256, age),
+            Err(e) => {
+                log::warn!("failed to find uncle author: {:?}", e);
+            }
+        }
+    }
+}
+
+/// stores the author of an uncle. Reward during the `fn finalize()` phase.
+/// Uncles older than the config's `MaxUncleAge` are not rewarded.
+pub(crate) fn note_uncle_author<T: Config>(uncle_author: H256, age: T::BlockNumber) {
+    if age.is_zero() || age > T::MaxUncleAge::get() {
+        log::debug!("uncle of age {:?} will not be rewarded", age);
+        return;
     }
+    <UncleAuthors<T>>::append((uncle_author, age));
 }
 
 /// checks at what period the given block number belongs to.
 /// If it exceeds to the maximum value of u8 datatype,
 /// reduction_fraction is already way over 100%.
-fn increase_reduction_fraction<T: Config>(block_number: T::BlockNumber) -> Option<u8> {
-    let reduction_period: T::BlockNumber = T::RewardReductionPeriod::get();
-
+fn increase_reduction_fraction<BlockNumber: AtLeast32BitUnsigned + Copy>(
+    block_number: BlockNumber,
+    reduction_period: BlockNumber,
+) -> Option<u8> {
     // When reduction_period is not set, there's no such thing as "block period".
     // There's no need to compute for the "current" block period.
     // Reward will not decrease at all.
@@ -75,13 +187,24 @@ fn increase_reduction_fraction<T: Config>(block_number: T::BlockNumber) -> Optio
     }
 }
 
-/// Returns the newly reduced reward amount for a Block Author.
-/// How much a reward is reduced, will be based on the config's`RewardReductionFraction`.
-fn get_block_author_reward<T: Config>(block_number: T::BlockNumber) -> Value {
-    let reduction_fraction = T::RewardReductionFraction::get().deconstruct();
-    let last_block_rewarded_period = (100u8 / reduction_fraction) - 1;
+/// Returns the reward at the given block number, reduced by `reduction_fraction`
+/// every `reduction_period`.
+fn reduced_reward<BlockNumber: AtLeast32BitUnsigned + Copy>(
+    block_number: BlockNumber,
+    initial_reward: Value,
+    minimum_reward: Value,
+    reduction_fraction: Percent,
+    reduction_period: BlockNumber,
+) -> Value {
+    let reduction_fraction = reduction_fraction.deconstruct();
+
+    // without a reduction fraction, the reward never changes.
+    if reduction_fraction == 0 {
+        return initial_reward;
+    }
+    let last_block_rewarded_period = (100u8 / reduction_fraction).saturating_sub(1);
 
-    match increase_reduction_fraction::<T>(block_number) {
+    match increase_reduction_fraction(block_number, reduction_period) {
         None => {
             // cannot increase the current reduction fraction anymore; it has exceeded 100%.
         }
@@ -92,51 +215,184 @@ fn get_block_author_reward<T: Config>(block_number: T::BlockNumber) -> Value {
                 let updated_reduction_fraction =
                     Percent::from_percent(reduction_fraction * current_block_period);
 
-                let reward_amount = T::InitialReward::get();
-                return reward_amount - updated_reduction_fraction.mul_ceil(reward_amount);
+                return initial_reward - updated_reduction_fraction.mul_ceil(initial_reward);
             }
         }
     }
 
-    T::DefaultMinimumReward::get()
+    minimum_reward
+}
+
+/// Returns the newly reduced reward amount for a Block Author.
+/// How much a reward is reduced, will be based on the `RewardSchedule`.
+pub(crate) fn get_block_author_reward<T: Config>(block_number: T::BlockNumber) -> Value {
+    <RewardSchedule<T>>::get().reward_at(block_number)
+}
+
+/// Computes the outpoint of a reward utxo.
+/// The `kind` separates the different kinds of rewards given in the same block.
+pub(crate) fn reward_outpoint<T: Config>(
+    block_number: T::BlockNumber,
+    utxo: &TransactionOutputFor<T>,
+    kind: &'static str,
+) -> H256 {
+    //TODO: https://github.com/mintlayer/core/pull/83#discussion_r742773343
+    let b_num = block_number.saturated_into::<u64>();
+    BlakeTwo256::hash_of(&(utxo, b_num, kind))
 }
 
+/// Inserts the reward utxo to the `UtxoStore`.
+/// Returns false if the utxo was not inserted.
 fn insert_to_utxo_store<T: Config>(
     block_number: T::BlockNumber,
-    block_author: H256,
-    reward: Value,
-) {
-    let utxo = TransactionOutput::new_pubkey(reward, block_author.clone());
+    utxo: &TransactionOutputFor<T>,
+    kind: &'static str,
+) -> bool {
+    // no need to create utxos without any value.
+    if utxo.value.is_zero() {
+        return false;
+    }
 
-    //TODO: https://github.com/mintlayer/core/pull/83#discussion_r742773343
-    let hash = {
-        let b_num = block_number.saturated_into::<u64>();
-        BlakeTwo256::hash_of(&(&utxo, b_num, "author_reward"))
-    };
+    let hash = reward_outpoint::<T>(block_number, utxo, kind);
+    if <UtxoStore<T>>::contains_key(hash) {
+        log::warn!("reward utxo {:?} already exists", hash);
+        // the reward has been issued, but can never be spent.
+        supply::note_mlt_burned::<T>(utxo.value);
+        return false;
+    }
+
+    <UtxoStore<T>>::insert(hash, utxo);
+    // the reward can only be spent after `RewardMaturity` blocks.
+    <RewardUtxoHeight<T>>::insert(hash, block_number);
+    true
+}
+
+/// Divides the reward among the stakers, pro-rata to their total locked utxos.
+/// Returns the amount that was not given away, either because there are no stakers or
+/// because of rounding.
+fn reward_stakers<T: Config>(block_number: T::BlockNumber, stakers_reward: Value) -> Value {
+    let stakers: Vec<(T::AccountId, Value)> =
+        <StakingCount<T>>::iter().map(|(stash, (_, total))| (stash, total)).collect();
+    let total_stake = stakers.iter().fold(0 as Value, |acc, (_, stake)| acc.saturating_add(*stake));
+
+    if total_stake.is_zero() {
+        return stakers_reward;
+    }
+
+    let mut remainder = stakers_reward;
+    for (stash_account, stake) in stakers {
+        let share = match multiply_by_rational(stakers_reward, stake, total_stake) {
+            Ok(share) => share,
+            Err(e) => {
+                log::warn!(
+                    "failed to compute the reward of {:?}: {:?}",
+                    stash_account,
+                    e
+                );
+                continue;
+            }
+        };
+
+        let stash_pubkey = match convert_to_h256::<T>(&stash_account) {
+            Ok(pubkey) => pubkey,
+            Err(e) => {
+                log::warn!("failed to reward staker {:?}: {:?}", stash_account, e);
+                continue;
+            }
+        };
 
-    if !<UtxoStore<T>>::contains_key(hash) {
-        <UtxoStore<T>>::insert(hash, utxo.clone());
+        let utxo = TransactionOutput::new_pubkey(share, stash_pubkey);
+        if insert_to_utxo_store::<T>(block_number, &utxo, "staker_reward") {
+            remainder = remainder.saturating_sub(share);
+            <Pallet<T>>::deposit_event(Event::<T>::StakerRewarded(utxo, stash_account));
+        }
+    }
+
+    remainder
+}
+
+/// Divides the reward based on the config's `RewardDistribution`.
+/// Whatever is not given to the block author and the stakers, goes to the treasury.
+fn distribute_reward<T: Config>(block_number: T::BlockNumber, block_author: H256, reward: Value) {
+    let distribution = T::RewardDistribution::get();
+
+    let author_reward = distribution.author.mul_floor(reward);
+    let stakers_reward = distribution.stakers.mul_floor(reward).min(reward - author_reward);
+    let mut treasury_reward = reward - author_reward - stakers_reward;
 
+    let utxo = TransactionOutput::new_pubkey(author_reward, block_author);
+    if insert_to_utxo_store::<T>(block_number, &utxo, "author_reward") {
         <Pallet<T>>::deposit_event(Event::<T>::BlockAuthorRewarded(utxo));
     }
+
+    treasury_reward =
+        treasury_reward.saturating_add(reward_stakers::<T>(block_number, stakers_reward));
+
+    reward_treasury::<T>(block_number, treasury_reward);
+}
+
+/// Gives the reward to the treasury, as found in the config's `RewardDistribution`.
+fn reward_treasury<T: Config>(block_number: T::BlockNumber, reward: Value) {
+    let treasury = T::RewardDistribution::get().treasury;
+    let utxo = TransactionOutput::new_script_hash(reward, treasury);
+    if insert_to_utxo_store::<T>(block_number, &utxo, "treasury_reward") {
+        <Pallet<T>>::deposit_event(Event::<T>::TreasuryRewarded(utxo));
+    }
+}
+
+/// Rewards the authors of the uncles included in the block, each with the config's
+/// `UncleRewardFraction` of the block reward. Transaction fees are not shared with uncles.
+fn reward_uncle_authors<T: Config>(block_number: T::BlockNumber, block_reward: Value) {
+    let uncle_reward = T::UncleRewardFraction::get().mul_floor(block_reward);
+
+    // an author can have more than one uncle in the same block.
+    let mut rewards: BTreeMap<H256, Value> = BTreeMap::new();
+    for (uncle_author, _) in <UncleAuthors<T>>::take() {
+        let reward = rewards.entry(uncle_author).or_insert(0);
+        *reward = reward.saturating_add(uncle_reward);
+    }
+
+    for (uncle_author, reward) in rewards {
+        supply::note_mlt_issued::<T>(reward);
+        let utxo = TransactionOutput::new_pubkey(reward, uncle_author);
+        if insert_to_utxo_store::<T>(block_number, &utxo, "uncle_reward") {
+            <Pallet<T>>::deposit_event(Event::<T>::UncleAuthorRewarded(utxo));
+        }
+    }
 }
 
-/// Rewards the block author with a utxo of value based on the `BlockAuthorRewardAmount`
-/// and the transaction fees.
+/// Rewards the block author, the stakers and the treasury with utxos of value based on
+/// the `BlockAuthorRewardAmount` and the transaction fees.
 pub(crate) fn reward_block_author<T: Config>(block_number: T::BlockNumber) {
     // As written on the definition of Take:
     // Take a value from storage, removing it afterwards.
     // This is taking a value of the RewardTotal storage, freeing it up.
     let transaction_fees = <RewardTotal<T>>::take();
+    let block_reward = get_block_author_reward::<T>(block_number);
+
+    reward_uncle_authors::<T>(block_number, block_reward);
+
+    if let Some(reward_amount) = block_reward.checked_add(transaction_fees) {
+        // only the block reward is new; the fees are already in circulation.
+        supply::note_mlt_issued::<T>(block_reward);
 
-    if let Some(reward_amount) =
-        get_block_author_reward::<T>(block_number).checked_add(transaction_fees)
-    {
         // As written on the definition of Take:
         // Take a value from storage, removing it afterwards.
         // This is taking a value of the BlockAuthor storage, freeing it up.
-        let block_author = <BlockAuthor<T>>::take().expect("Block author found.");
-        insert_to_utxo_store::<T>(block_number, block_author, reward_amount)
+        match <BlockAuthor<T>>::take() {
+            Some(block_author) => distribute_reward::<T>(block_number, block_author, reward_amount),
+            None => {
+                log::warn!("block author not found; the reward goes to the fallback.");
+                <Pallet<T>>::deposit_event(Event::<T>::BlockAuthorMissing(reward_amount));
+
+                match T::MissingAuthorPolicy::get() {
+                    MissingAuthorPolicy::CarryOver => <RewardTotal<T>>::put(reward_amount),
+                    MissingAuthorPolicy::Treasury => {
+                        reward_treasury::<T>(block_number, reward_amount)
+                    }
+                }
+            }
+        }
     } else {
         //TODO: what's the actual behaviour (or if this happens at all)
         log::warn!("problem adding the block author reward and the fees.");
@@ -149,31 +405,35 @@ pub(crate) fn reward_block_author<T: Config>(block_number: T::BlockNumber) {
 mod test {
     use super::*;
     use crate::mock::*;
+    use crate::{Transaction, TransactionInput};
+    use frame_support::{assert_err, assert_noop, assert_ok, sp_io::crypto};
+    use sp_core::{sp_std::vec, testing::SR25519};
+    use sp_runtime::DispatchError;
 
     #[test]
     fn increase_reduction_fraction_test() {
         alice_test_ext().execute_with(|| {
             // with ReductionPeriod = 5:
             // at Block 3, period is at 0, since 3 < 5
-            assert_eq!(increase_reduction_fraction::<Test>(3), Some(0));
+            assert_eq!(increase_reduction_fraction(3, RewardReductionPeriod::get()), Some(0));
 
             // at Block 3, period is at 0, since 4 < 5
-            assert_eq!(increase_reduction_fraction::<Test>(4), Some(0));
+            assert_eq!(increase_reduction_fraction(4, RewardReductionPeriod::get()), Some(0));
 
             // at Block 5, period is at 1, since 5/ReductionPeriod = 1.
-            assert_eq!(increase_reduction_fraction::<Test>(5), Some(1));
+            assert_eq!(increase_reduction_fraction(5, RewardReductionPeriod::get()), Some(1));
 
             // at Block 3, period is at 2
-            assert_eq!(increase_reduction_fraction::<Test>(10), Some(2));
+            assert_eq!(increase_reduction_fraction(10, RewardReductionPeriod::get()), Some(2));
 
             // at Block 20, period is at 4
-            assert_eq!(increase_reduction_fraction::<Test>(20), Some(4));
+            assert_eq!(increase_reduction_fraction(20, RewardReductionPeriod::get()), Some(4));
 
             // at Block 53, period is at 10, since 53/ReductionPeriod = 10 (nevermind the remainder)
-            assert_eq!(increase_reduction_fraction::<Test>(53), Some(10));
+            assert_eq!(increase_reduction_fraction(53, RewardReductionPeriod::get()), Some(10));
 
             // at Block 42000, multiplication factor of the reduction fraction is way off. (nevermind the remainder)
-            assert_eq!(increase_reduction_fraction::<Test>(4200), None);
+            assert_eq!(increase_reduction_fraction(4200, RewardReductionPeriod::get()), None);
         });
     }
 
@@ -215,4 +475,222 @@ mod test {
             assert_eq!(get_block_author_reward::<Test>(5000), 1);
         });
     }
+
+    #[test]
+    fn reward_maturity_test() {
+        alice_test_ext().execute_with(|| {
+            let alice_pub_key = crypto::sr25519_public_keys(SR25519)[0];
+            let alice_h256 = H256::from(alice_pub_key);
+
+            frame_system::Pallet::<Test>::set_block_number(1);
+            <BlockAuthor<Test>>::put(alice_h256);
+            reward_block_author::<Test>(1);
+
+            // at Block 1, the reward is 100 and there are no fees. The author gets 60% of it.
+            let reward_utxo = TransactionOutput::new_pubkey(60, alice_h256);
+            let reward_hash = reward_outpoint::<Test>(1, &reward_utxo, "author_reward");
+            assert_eq!(UtxoStore::<Test>::get(reward_hash), Some(reward_utxo.clone()));
+            assert_eq!(
+                Utxo::utxo_maturity(reward_hash),
+                Some(1 + RewardMaturity::get())
+            );
+
+            let tx = Transaction {
+                inputs: vec![TransactionInput::new_empty(reward_hash)],
+                outputs: vec![TransactionOutput::new_pubkey(60, alice_h256)],
+                time_lock: Default::default(),
+            }
+            .sign(&[reward_utxo], 0, &alice_pub_key)
+            .expect("alice's pub key not found");
+
+            // the reward cannot be spent in the next blocks.
+            frame_system::Pallet::<Test>::set_block_number(2);
+            assert_err!(
+                Utxo::spend(Origin::signed(H256::zero()), tx.clone()),
+                "block reward utxo has not matured yet"
+            );
+
+            // once matured, the reward can be spent like any other utxo.
+            frame_system::Pallet::<Test>::set_block_number(1 + RewardMaturity::get());
+            assert_ok!(Utxo::spend(Origin::signed(H256::zero()), tx));
+            assert!(!UtxoStore::<Test>::contains_key(reward_hash));
+            assert_eq!(Utxo::utxo_maturity(reward_hash), None);
+        });
+    }
+
+    #[test]
+    fn reward_distribution_test() {
+        let (mut test_ext, keys_and_hashes) = multiple_keys_test_ext();
+        test_ext.execute_with(|| {
+            let (alice_pub_key, _) = keys_and_hashes[0];
+            let (karl_pub_key, _) = keys_and_hashes[1];
+            let (greg_pub_key, _) = keys_and_hashes[2];
+            let alice_h256 = H256::from(alice_pub_key);
+            let karl_h256 = H256::from(karl_pub_key);
+            let greg_h256 = H256::from(greg_pub_key);
+
+            // alice has staked 10 at genesis. Let karl stake 30.
+            <StakingCount<Test>>::insert(karl_h256, (1, 30));
+
+            // greg authors Block 1; the reward is 100 and there are 20 in fees.
+            <RewardTotal<Test>>::put(20);
+            <BlockAuthor<Test>>::put(greg_h256);
+            reward_block_author::<Test>(1);
+
+            let reward_exists = |utxo: TransactionOutputFor<Test>, kind: &'static str| {
+                UtxoStore::<Test>::get(reward_outpoint::<Test>(1, &utxo, kind)) == Some(utxo)
+            };
+
+            // the author gets 60% of 120.
+            assert!(reward_exists(
+                TransactionOutput::new_pubkey(72, greg_h256),
+                "author_reward"
+            ));
+
+            // the stakers divide 30% of 120 pro-rata: 36 * 10/40 for alice, 36 * 30/40 for karl.
+            assert!(reward_exists(
+                TransactionOutput::new_pubkey(9, alice_h256),
+                "staker_reward"
+            ));
+            assert!(reward_exists(
+                TransactionOutput::new_pubkey(27, karl_h256),
+                "staker_reward"
+            ));
+
+            // the rest goes to the treasury.
+            assert!(reward_exists(
+                TransactionOutput::new_script_hash(12, BlockRewardDistribution::get().treasury),
+                "treasury_reward"
+            ));
+
+            assert_eq!(<RewardTotal<Test>>::get(), 0);
+            assert_eq!(<BlockAuthor<Test>>::get(), None);
+        });
+    }
+
+    #[test]
+    fn uncle_reward_test() {
+        let (mut test_ext, keys_and_hashes) = multiple_keys_test_ext();
+        test_ext.execute_with(|| {
+            let karl_h256 = H256::from(keys_and_hashes[1].0);
+            let greg_h256 = H256::from(keys_and_hashes[2].0);
+            let tom_h256 = H256::from(keys_and_hashes[3].0);
+
+            // with MaxUncleAge = 2, only karl's uncles will be rewarded.
+            note_uncle_author::<Test>(karl_h256, 1);
+            note_uncle_author::<Test>(karl_h256, 2);
+            note_uncle_author::<Test>(tom_h256, 0);
+            note_uncle_author::<Test>(tom_h256, 3);
+            assert_eq!(Utxo::uncle_authors(), vec![(karl_h256, 1), (karl_h256, 2)]);
+
+            <BlockAuthor<Test>>::put(greg_h256);
+            reward_block_author::<Test>(1);
+
+            // at Block 1, the reward is 100. Each uncle is rewarded with 50% of it.
+            let uncle_utxo = TransactionOutput::new_pubkey(100, karl_h256);
+            let uncle_hash = reward_outpoint::<Test>(1, &uncle_utxo, "uncle_reward");
+            assert_eq!(UtxoStore::<Test>::get(uncle_hash), Some(uncle_utxo));
+            assert_eq!(Utxo::utxo_maturity(uncle_hash), Some(1 + RewardMaturity::get()));
+
+            // the block author's reward stays the same.
+            let author_utxo = TransactionOutput::new_pubkey(60, greg_h256);
+            let author_hash = reward_outpoint::<Test>(1, &author_utxo, "author_reward");
+            assert_eq!(UtxoStore::<Test>::get(author_hash), Some(author_utxo));
+
+            assert!(Utxo::uncle_authors().is_empty());
+        });
+    }
+
+    #[test]
+    fn emission_schedule_test() {
+        alice_test_ext().execute_with(|| {
+            let steps = EmissionSchedule::Steps(vec![(0, 50), (10, 20), (20, 0)]);
+            assert_ok!(steps.validate());
+            assert_eq!(steps.reward_at(0), 50);
+            assert_eq!(steps.reward_at(9), 50);
+            assert_eq!(steps.reward_at(10), 20);
+            assert_eq!(steps.reward_at(5000), 0);
+
+            // steps not starting at Block 0 do not give any reward before the first step.
+            let late_steps = EmissionSchedule::Steps(vec![(5u64, 50)]);
+            assert_eq!(late_steps.reward_at(4), 0);
+            assert_eq!(late_steps.reward_at(5), 50);
+
+            assert_err!(
+                EmissionSchedule::<u64>::Steps(vec![]).validate(),
+                "emission schedule has no steps"
+            );
+            assert_err!(
+                EmissionSchedule::Steps(vec![(0u64, 50), (10, 20), (10, 0)]).validate(),
+                "emission schedule steps must be in ascending order of start block"
+            );
+            assert_err!(
+                EmissionSchedule::Reduction {
+                    initial_reward: 10,
+                    minimum_reward: 20,
+                    reduction_fraction: Percent::from_percent(25),
+                    reduction_period: 5u64,
+                }
+                .validate(),
+                "minimum reward must not exceed the initial reward"
+            );
+
+            // a 0% reduction fraction keeps the reward unchanged.
+            let no_reduction = EmissionSchedule::Reduction {
+                initial_reward: 100,
+                minimum_reward: 1,
+                reduction_fraction: Percent::from_percent(0),
+                reduction_period: 5u64,
+            };
+            assert_eq!(no_reduction.reward_at(5000), 100);
+
+            // only root can change the schedule.
+            assert_noop!(
+                Utxo::set_emission_schedule(Origin::signed(H256::zero()), steps.clone()),
+                DispatchError::BadOrigin
+            );
+            assert_noop!(
+                Utxo::set_emission_schedule(Origin::root(), EmissionSchedule::Steps(vec![])),
+                "emission schedule has no steps"
+            );
+            assert_ok!(Utxo::set_emission_schedule(Origin::root(), steps.clone()));
+            assert_eq!(Utxo::reward_schedule(), steps);
+            assert_eq!(get_block_author_reward::<Test>(12), 20);
+        });
+    }
+
+    #[test]
+    fn missing_block_author_test() {
+        alice_test_ext().execute_with(|| {
+            // events are not recorded at Block 0.
+            System::set_block_number(1);
+
+            // with no block author, the reward and the fees are added to the next block's fees.
+            <RewardTotal<Test>>::put(20);
+            reward_block_author::<Test>(1);
+            assert_eq!(<RewardTotal<Test>>::get(), 120);
+            let missing_event = crate::mock::Event::Utxo(crate::Event::BlockAuthorMissing(120));
+            assert!(System::events().iter().any(|record| record.event == missing_event));
+
+            // the next block author gets all of it.
+            let alice_h256 = H256::from(crypto::sr25519_public_keys(SR25519)[0]);
+            <BlockAuthor<Test>>::put(alice_h256);
+            reward_block_author::<Test>(2);
+            let author_utxo = TransactionOutput::new_pubkey(132, alice_h256);
+            let author_hash = reward_outpoint::<Test>(2, &author_utxo, "author_reward");
+            assert_eq!(UtxoStore::<Test>::get(author_hash), Some(author_utxo));
+
+            // or the treasury gets all of it.
+            MissingAuthor::set(&MissingAuthorPolicy::Treasury);
+            <RewardTotal<Test>>::put(20);
+            reward_block_author::<Test>(3);
+            assert_eq!(<RewardTotal<Test>>::get(), 0);
+            let treasury_utxo = TransactionOutput::new_script_hash(
+                120,
+                BlockRewardDistribution::get().treasury,
+            );
+            let treasury_hash = reward_outpoint::<Test>(3, &treasury_utxo, "treasury_reward");
+            assert_eq!(UtxoStore::<Test>::get(treasury_hash), Some(treasury_utxo));
+        });
+    }
 }
@@ -16,22 +16,49 @@
 // Author(s): C. Yap
 
 use crate::{
-    convert_to_h256, tokens::Value, Config, Destination, Error, Event, LockedUtxos, Pallet,
-    RewardTotal, StakingCount, TransactionOutput, UtxoStore,
+    convert_to_h256, supply, tokens::Value, Config, Destination, Error, Event, LockedUtxos, Pallet,
+    PartiallyUnlockedStake, RewardTotal, StakingCount, StakingRewardDestination, TransactionOutput,
+    UtxoStore, WeightInfo,
 };
+use codec::{Decode, Encode};
 use frame_support::{
     dispatch::{DispatchResultWithPostInfo, Vec},
     ensure, fail,
     traits::Get,
+    RuntimeDebug,
 };
+#[cfg(feature = "std")]
+use serde::{Deserialize, Serialize};
 use sp_core::H256;
 use sp_runtime::traits::{BlakeTwo256, Hash};
+use sp_runtime::Perbill;
 use sp_runtime::transaction_validity::{TransactionLongevity, ValidTransaction};
+use sp_runtime::DispatchError;
 use sp_std::vec;
 
-use crate::staking::utils::remove_locked_utxos;
+use crate::staking::utils::{get_all_locked_utxo_outpoints, remove_locked_utxos};
 pub use validation::*;
 
+/// The preferences of a validator. see `ValidatorPrefs` of pallet-staking.
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
+#[derive(Clone, Encode, Decode, Eq, PartialEq, PartialOrd, Ord, RuntimeDebug, Default)]
+pub struct ValidatorPreferences {
+    /// the validator's cut of the era reward, before the rest is shared with its nominators.
+    pub commission: Perbill,
+    /// whether the validator refuses new nominations.
+    pub blocked: bool,
+}
+
+/// Where the value slashed from the locked utxos goes.
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
+#[derive(Clone, Copy, Encode, Decode, Eq, PartialEq, RuntimeDebug)]
+pub enum SlashPolicy {
+    /// the slashed value can never be spent again.
+    Burn,
+    /// the slashed value goes to the treasury of the `RewardDistribution`.
+    Treasury,
+}
+
 /// A helper trait to handle staking NOT found in pallet-utxo.
 pub trait StakingHelper<AccountId> {
     fn get_controller_account(stash_account: &AccountId) -> Result<AccountId, &'static str>;
@@ -40,8 +67,12 @@ pub trait StakingHelper<AccountId> {
 
     fn can_decode_session_key(session_key: &Vec<u8>) -> bool;
 
+    /// whether there is still an active stake, even if part of it is being unlocked.
     fn are_funds_locked(controller_account: &AccountId) -> bool;
 
+    /// whether some of the stake is unlocked, waiting to be withdrawn.
+    fn has_unlocking_funds(controller_account: &AccountId) -> bool;
+
     fn check_accounts_matched(controller_account: &AccountId, stash_account: &AccountId) -> bool;
 
     /// start the staking.
@@ -52,11 +83,19 @@ pub trait StakingHelper<AccountId> {
     /// In `pallet-staking`, its job is like an "accountant" to the stash account.
     /// * `session_key` - to get up-to-date with validators, eras, sessions. see `pallet-session`.
     /// * `value` - the amount to stake/bond/stash
+    /// * `validator_prefs` - the commission, and whether to accept nominations.
     fn lock_for_staking(
         stash_account: &AccountId,
         controller_account: &AccountId,
         session_key: &Vec<u8>,
         value: Value,
+        validator_prefs: &ValidatorPreferences,
+    ) -> DispatchResultWithPostInfo;
+
+    /// updates the preferences of an existing validator.
+    fn set_validator_preferences(
+        stash_account: &AccountId,
+        validator_prefs: &ValidatorPreferences,
     ) -> DispatchResultWithPostInfo;
 
     /// stake more funds for the validator
@@ -65,10 +104,65 @@ pub trait StakingHelper<AccountId> {
         value: Value,
     ) -> DispatchResultWithPostInfo;
 
+    /// replaces the session keys of an existing validator.
+    fn rotate_session_keys(
+        stash_account: &AccountId,
+        session_key: &Vec<u8>,
+    ) -> DispatchResultWithPostInfo;
+
+    /// assigns a new controller account to the stash account.
+    fn change_controller(
+        stash_account: &AccountId,
+        controller_account: &AccountId,
+    ) -> DispatchResultWithPostInfo;
+
+    /// checks whether all the targets are validators, and that there are not too many of them.
+    fn are_valid_nomination_targets(targets: &Vec<AccountId>) -> bool;
+
+    /// start nominating.
+    /// # Arguments
+    /// * `stash_account` - holds the funds. It is also its own controller.
+    /// * `targets` - the stash accounts of the validators to nominate.
+    /// * `value` - the amount to stake/bond/stash
+    fn lock_for_nominating(
+        stash_account: &AccountId,
+        targets: &Vec<AccountId>,
+        value: Value,
+    ) -> DispatchResultWithPostInfo;
+
+    /// replaces the validators nominated by the stash account.
+    fn nominate(stash_account: &AccountId, targets: &Vec<AccountId>) -> DispatchResultWithPostInfo;
+
+    /// locks back all of the unlocked stake, that has not been withdrawn yet.
+    fn rebond(stash_account: &AccountId) -> DispatchResultWithPostInfo;
+
+    /// stops validating or nominating, and unlocks the whole stake.
     fn unlock_request_for_withdrawal(stash_account: &AccountId) -> DispatchResultWithPostInfo;
 
+    /// unlock only the given value of the stake. The validator keeps on validating.
+    fn partial_unlock_request_for_withdrawal(
+        stash_account: &AccountId,
+        value: Value,
+    ) -> DispatchResultWithPostInfo;
+
     /// transfer balance from the locked state to the actual free balance.
     fn withdraw(stash_account: &AccountId) -> DispatchResultWithPostInfo;
+
+    /// transfer the partially unlocked balance to the actual free balance.
+    /// The rest of the stake remains locked.
+    fn withdraw_partial(stash_account: &AccountId) -> DispatchResultWithPostInfo;
+
+    /// stops validating or nominating, and removes the stake right away, without waiting
+    /// for the bonding duration. For a stake slashed until nothing is left to withdraw.
+    fn remove_stake(stash_account: &AccountId) -> DispatchResultWithPostInfo;
+
+    /// pays out the rewards of the given era, to the validator and its nominators.
+    /// The rewards are taken out of the outside staking logic, and returned
+    /// as a list of (stash account, reward), to be turned into utxos.
+    fn payout_stakers(
+        validator_stash: &AccountId,
+        era: u32,
+    ) -> Result<Vec<(AccountId, Value)>, DispatchError>;
 }
 
 /// Calls the outside staking logic to lock some funds
@@ -81,6 +175,7 @@ pub(crate) fn lock_for_staking<T: Config>(
         stash_account,
         controller_account,
         session_key,
+        validator_prefs,
     } = &output.destination
     {
         T::StakingHelper::lock_for_staking(
@@ -88,6 +183,7 @@ pub(crate) fn lock_for_staking<T: Config>(
             controller_account,
             session_key,
             output.value,
+            validator_prefs,
         )?;
         return utils::add_to_locked_utxos::<T>(hash_key, output, stash_account);
     }
@@ -111,8 +207,25 @@ pub(crate) fn lock_extra_for_staking<T: Config>(
     fail!(Error::<T>::InvalidOperation)
 }
 
+/// Calls the outside staking logic to lock some funds and nominate the given validators.
+/// Adds the transaction output to the `LockedUtxos` storage and `StakingCount` storage.
+pub(crate) fn lock_for_nominating<T: Config>(
+    hash_key: H256,
+    output: &TransactionOutput<T::AccountId>,
+) -> DispatchResultWithPostInfo {
+    if let Destination::LockForNominating {
+        stash_account,
+        targets,
+    } = &output.destination
+    {
+        T::StakingHelper::lock_for_nominating(stash_account, targets, output.value)?;
+        return utils::add_to_locked_utxos::<T>(hash_key, output, stash_account);
+    }
+    fail!(Error::<T>::InvalidOperation)
+}
+
 /// unlocking the staked funds outside of the `pallet-utxo`.
-/// also means you don't want to be a validator anymore.
+/// also means you don't want to be a validator or a nominator anymore.
 pub(crate) fn unlock_request_for_withdrawal<T: Config>(
     stash_account: T::AccountId,
 ) -> DispatchResultWithPostInfo {
@@ -123,12 +236,160 @@ pub(crate) fn unlock_request_for_withdrawal<T: Config>(
     Ok(res)
 }
 
+/// unlocking only a part of the staked funds outside of the `pallet-utxo`.
+/// The stash account remains a validator, with the rest of its stake.
+pub(crate) fn partial_unlock_request_for_withdrawal<T: Config>(
+    stash_account: T::AccountId,
+    value: Value,
+) -> DispatchResultWithPostInfo {
+    validate_partial_unlock_request_for_withdrawal::<T>(&stash_account, value)?;
+
+    let res = T::StakingHelper::partial_unlock_request_for_withdrawal(&stash_account, value)?;
+    <PartiallyUnlockedStake<T>>::insert(stash_account.clone(), value);
+    <Pallet<T>>::deposit_event(Event::<T>::StakePartiallyUnlocked(value, stash_account));
+    Ok(res)
+}
+
+/// Changes the preferences of the validator outside of the `pallet-utxo`.
+/// The new preferences are also kept in its `LockForStaking` utxo.
+pub(crate) fn set_validator_preferences<T: Config>(
+    stash_account: T::AccountId,
+    validator_prefs: ValidatorPreferences,
+) -> DispatchResultWithPostInfo {
+    validate_set_validator_preferences::<T>(&stash_account)?;
+
+    let res = T::StakingHelper::set_validator_preferences(&stash_account, &validator_prefs)?;
+
+    for outpoint in get_all_locked_utxo_outpoints::<T>(&stash_account) {
+        <LockedUtxos<T>>::mutate(outpoint, |utxo| {
+            if let Some(TransactionOutput {
+                destination:
+                    Destination::LockForStaking {
+                        validator_prefs: prefs,
+                        ..
+                    },
+                ..
+            }) = utxo
+            {
+                *prefs = validator_prefs.clone();
+            }
+        });
+    }
+
+    <Pallet<T>>::deposit_event(Event::<T>::ValidatorPreferencesChanged(
+        stash_account,
+        validator_prefs,
+    ));
+    Ok(res)
+}
+
+/// Replaces the session keys of the validator outside of the `pallet-utxo`.
+/// The new session keys are also kept in its `LockForStaking` utxo.
+pub(crate) fn rotate_session_keys<T: Config>(
+    stash_account: T::AccountId,
+    session_key: Vec<u8>,
+) -> DispatchResultWithPostInfo {
+    validate_rotate_session_keys::<T>(&stash_account, &session_key)?;
+
+    let res = T::StakingHelper::rotate_session_keys(&stash_account, &session_key)?;
+
+    for outpoint in get_all_locked_utxo_outpoints::<T>(&stash_account) {
+        <LockedUtxos<T>>::mutate(outpoint, |utxo| {
+            if let Some(TransactionOutput {
+                destination:
+                    Destination::LockForStaking {
+                        session_key: key, ..
+                    },
+                ..
+            }) = utxo
+            {
+                *key = session_key.clone();
+            }
+        });
+    }
+
+    <Pallet<T>>::deposit_event(Event::<T>::SessionKeysRotated(stash_account));
+    Ok(res)
+}
+
+/// Assigns a new controller account to the validator outside of the `pallet-utxo`.
+/// The new controller account is also kept in its locked utxos.
+pub(crate) fn change_controller<T: Config>(
+    stash_account: T::AccountId,
+    controller_account: T::AccountId,
+) -> DispatchResultWithPostInfo {
+    validate_change_controller::<T>(&stash_account, &controller_account)?;
+
+    let res = T::StakingHelper::change_controller(&stash_account, &controller_account)?;
+
+    for outpoint in get_all_locked_utxo_outpoints::<T>(&stash_account) {
+        <LockedUtxos<T>>::mutate(outpoint, |utxo| match utxo {
+            Some(TransactionOutput {
+                destination:
+                    Destination::LockForStaking {
+                        controller_account: controller,
+                        ..
+                    },
+                ..
+            })
+            | Some(TransactionOutput {
+                destination:
+                    Destination::LockExtraForStaking {
+                        controller_account: controller,
+                        ..
+                    },
+                ..
+            }) => {
+                *controller = controller_account.clone();
+            }
+            _ => {}
+        });
+    }
+
+    <Pallet<T>>::deposit_event(Event::<T>::ControllerChanged(
+        stash_account,
+        controller_account,
+    ));
+    Ok(res)
+}
+
+/// Cancels the pending unlock of the stake outside of the `pallet-utxo`.
+/// If the whole stake was unlocked, the stash account validates with its stored preferences,
+/// or nominates its stored targets, again.
+pub(crate) fn rebond<T: Config>(stash_account: T::AccountId) -> DispatchResultWithPostInfo {
+    validate_rebond::<T>(&stash_account)?;
+
+    let res = T::StakingHelper::rebond(&stash_account)?;
+
+    // a partial unlock never stopped the validating or nominating.
+    if <PartiallyUnlockedStake<T>>::take(&stash_account).is_none() {
+        match utils::get_stake_destination::<T>(&stash_account) {
+            Some(Destination::LockForStaking {
+                validator_prefs, ..
+            }) => {
+                T::StakingHelper::set_validator_preferences(&stash_account, &validator_prefs)?;
+            }
+            Some(Destination::LockForNominating { targets, .. }) => {
+                T::StakingHelper::nominate(&stash_account, &targets)?;
+            }
+            _ => fail!("No locked utxos found for the stash account"),
+        }
+    }
+
+    <Pallet<T>>::deposit_event(Event::<T>::StakeRebonded(stash_account));
+    Ok(res)
+}
+
 /// Consolidates all unlocked utxos  into one, and moves it to `UtxoStore`.
 /// Make SURE that `fn unlock(...)` has been called and the era for withdrawal has passed, before
 /// performing a withdrawal.
 pub(crate) fn withdraw<T: Config>(stash_account: T::AccountId) -> DispatchResultWithPostInfo {
     validate_withdrawal::<T>(&stash_account)?;
 
+    if let Some(value) = <PartiallyUnlockedStake<T>>::get(&stash_account) {
+        return withdraw_partial::<T>(stash_account, value);
+    }
+
     let res = T::StakingHelper::withdraw(&stash_account)?;
 
     let stash_pubkey = convert_to_h256::<T>(&stash_account)?;
@@ -136,6 +397,8 @@ pub(crate) fn withdraw<T: Config>(stash_account: T::AccountId) -> DispatchResult
     // remove from the `StakingCount` storage
     let (_, mut total) =
         <StakingCount<T>>::take(stash_account.clone()).ok_or(Error::<T>::StashAccountNotFound)?;
+    // the fee goes back to circulation as well, through the `RewardTotal`.
+    supply::note_mlt_unlocked::<T>(total);
 
     let fee = T::StakeWithdrawalFee::get();
     total = total
@@ -143,6 +406,7 @@ pub(crate) fn withdraw<T: Config>(stash_account: T::AccountId) -> DispatchResult
         .ok_or("Total amount of Locked UTXOs is less than minimum?")?;
 
     let outpoints = remove_locked_utxos::<T>(&stash_account);
+    <StakingRewardDestination<T>>::remove(&stash_account);
     log::debug!(
         "removed a total of {} in the LockedUtxo storage.",
         outpoints.len()
@@ -161,12 +425,232 @@ pub(crate) fn withdraw<T: Config>(stash_account: T::AccountId) -> DispatchResult
     Ok(res)
 }
 
+/// Moves the partially unlocked value, minus the fee, to `UtxoStore`.
+/// The remaining stake is consolidated into one locked utxo.
+fn withdraw_partial<T: Config>(
+    stash_account: T::AccountId,
+    value: Value,
+) -> DispatchResultWithPostInfo {
+    // the withdrawn and the relocked utxos are keyed by the outpoints of the stake.
+    let outpoints = get_all_locked_utxo_outpoints::<T>(&stash_account);
+    let hash = BlakeTwo256::hash_of(&outpoints);
+    let locked_hash = BlakeTwo256::hash_of(&(&outpoints, "relocked"));
+    ensure!(
+        !<UtxoStore<T>>::contains_key(hash),
+        Error::<T>::OutpointAlreadyExists
+    );
+    ensure!(
+        !<LockedUtxos<T>>::contains_key(locked_hash),
+        Error::<T>::OutpointAlreadyExists
+    );
+
+    let res = T::StakingHelper::withdraw_partial(&stash_account)?;
+    <PartiallyUnlockedStake<T>>::remove(&stash_account);
+
+    let stash_pubkey = convert_to_h256::<T>(&stash_account)?;
+
+    let (_, total) =
+        <StakingCount<T>>::get(stash_account.clone()).ok_or(Error::<T>::StashAccountNotFound)?;
+    let remaining = total.checked_sub(value).ok_or("Unlocked value exceeds the total stake?")?;
+
+    let fee = T::StakeWithdrawalFee::get();
+    let withdrawn = value
+        .checked_sub(fee)
+        .ok_or("Unlocked value is less than the withdrawal fee?")?;
+
+    // keep the destination of the first stake, so its details are not lost.
+    let mut locked_utxos: Vec<TransactionOutput<T::AccountId>> =
+        outpoints.iter().filter_map(|outpoint| <LockedUtxos<T>>::take(outpoint)).collect();
+    locked_utxos.sort_by_key(|utxo| match utxo.destination {
+        Destination::LockForStaking { .. } | Destination::LockForNominating { .. } => 0,
+        _ => 1,
+    });
+    let destination = locked_utxos
+        .into_iter()
+        .next()
+        .map(|utxo| utxo.destination)
+        .ok_or("No locked utxos found for the stash account")?;
+
+    // the rest of the stake is locked again, as one utxo.
+    <LockedUtxos<T>>::insert(
+        locked_hash,
+        TransactionOutput {
+            value: remaining,
+            destination,
+            data: None,
+        },
+    );
+    <StakingCount<T>>::insert(stash_account.clone(), (1, remaining));
+
+    // the fee goes back to circulation as well, through the `RewardTotal`.
+    supply::note_mlt_unlocked::<T>(value);
+
+    let utxo = TransactionOutput::new_pubkey(withdrawn, stash_pubkey);
+    <UtxoStore<T>>::insert(hash, utxo);
+
+    // insert the fee into the reward total
+    let reward_total = <RewardTotal<T>>::take();
+    <RewardTotal<T>>::put(reward_total + fee);
+
+    <Pallet<T>>::deposit_event(Event::<T>::StakeWithdrawn(withdrawn, stash_account));
+    Ok(res)
+}
+
+/// Collects the era rewards of the validator and its nominators from the outside staking logic,
+/// and turns each of them into a utxo in the `UtxoStore`.
+/// A stash account receives its reward to its `StakingRewardDestination`, or to its own pubkey.
+pub(crate) fn payout_stakers<T: Config>(
+    validator_stash: T::AccountId,
+    era: u32,
+) -> DispatchResultWithPostInfo {
+    let payouts = T::StakingHelper::payout_stakers(&validator_stash, era)?;
+    let rewarded = payouts.len() as u32;
+
+    for (stash_account, value) in payouts {
+        if value == 0 {
+            continue;
+        }
+
+        let utxo = match <StakingRewardDestination<T>>::get(&stash_account) {
+            Some(destination) => TransactionOutput {
+                value,
+                destination,
+                data: None,
+            },
+            None => TransactionOutput::new_pubkey(value, convert_to_h256::<T>(&stash_account)?),
+        };
+
+        // `pallet-staking` only allows one payout per validator per era.
+        let hash = BlakeTwo256::hash_of(&(&validator_stash, era, &stash_account, "era_reward"));
+        ensure!(
+            !<UtxoStore<T>>::contains_key(hash),
+            Error::<T>::OutpointAlreadyExists
+        );
+
+        log::debug!("inserting era reward {:?} to UtxoStore as key {:?}", utxo, hash);
+        <UtxoStore<T>>::insert(hash, utxo.clone());
+        supply::note_mlt_issued::<T>(value);
+        <Pallet<T>>::deposit_event(Event::<T>::EraRewardPaid(utxo, stash_account, era));
+    }
+
+    Ok(Some(<T as Config>::WeightInfo::payout_stakers(rewarded)).into())
+}
+
+/// Sets where the era rewards of the stash account will go.
+pub(crate) fn set_staking_reward_destination<T: Config>(
+    stash_account: T::AccountId,
+    destination: Destination<T::AccountId>,
+) -> DispatchResultWithPostInfo {
+    ensure!(
+        <StakingCount<T>>::contains_key(&stash_account),
+        Error::<T>::StashAccountNotFound
+    );
+    ensure!(
+        matches!(
+            destination,
+            Destination::Pubkey(_) | Destination::ScriptHash(_)
+        ),
+        "era rewards can only be sent to a pubkey or a script hash"
+    );
+
+    <StakingRewardDestination<T>>::insert(stash_account, destination);
+    Ok(().into())
+}
+
+/// Reduces the locked utxos of the stash account by the value slashed outside of the `pallet-utxo`,
+/// so that withdrawing only returns what is left of the stake.
+/// The slashed value is burned, or sent to the treasury, depending on the config's `SlashPolicy`.
+/// When what is left cannot pay for the `StakeWithdrawalFee`, the stake is removed altogether,
+/// and the leftover goes to the `RewardTotal`, like the fee of a withdrawal.
+/// Returns the value actually slashed, which cannot be more than the stake.
+pub fn slash_locked_stake<T: Config>(stash_account: &T::AccountId, value: Value) -> Value {
+    let (num_of_utxos, total) = match <StakingCount<T>>::get(stash_account) {
+        Some(staking_count) => staking_count,
+        None => {
+            log::warn!("cannot slash {:?}: stash account not found.", stash_account);
+            return 0;
+        }
+    };
+
+    let slashed = value.min(total);
+    if slashed == 0 {
+        return 0;
+    }
+
+    let mut to_slash = slashed;
+    for outpoint in get_all_locked_utxo_outpoints::<T>(stash_account) {
+        if to_slash == 0 {
+            break;
+        }
+        <LockedUtxos<T>>::mutate(outpoint, |utxo| {
+            if let Some(utxo) = utxo {
+                let cut = to_slash.min(utxo.value);
+                utxo.value -= cut;
+                to_slash -= cut;
+            }
+        });
+    }
+
+    let remaining = total - slashed;
+    if remaining <= T::StakeWithdrawalFee::get() {
+        // nothing would be left to withdraw, so the stash stops staking altogether.
+        if let Err(e) = T::StakingHelper::remove_stake(stash_account) {
+            log::error!("failed to remove the stake of {:?}: {:?}", stash_account, e);
+        }
+        let outpoints = remove_locked_utxos::<T>(stash_account);
+        log::debug!(
+            "removed the {} locked utxos of the slashed stash {:?}",
+            outpoints.len(),
+            stash_account
+        );
+        <StakingCount<T>>::remove(stash_account);
+        <PartiallyUnlockedStake<T>>::remove(stash_account);
+        <StakingRewardDestination<T>>::remove(stash_account);
+
+        if remaining > 0 {
+            supply::note_mlt_unlocked::<T>(remaining);
+            <RewardTotal<T>>::mutate(|reward_total| *reward_total += remaining);
+        }
+    } else if let Some(unlocking) = <PartiallyUnlockedStake<T>>::get(stash_account) {
+        <StakingCount<T>>::insert(stash_account.clone(), (num_of_utxos, remaining));
+
+        // like in pallet-staking, the active stake is slashed first, before the unlocking value.
+        let active = total.saturating_sub(unlocking);
+        let unlocking = unlocking.saturating_sub(slashed.saturating_sub(active));
+        if unlocking == 0 {
+            <PartiallyUnlockedStake<T>>::remove(stash_account);
+        } else {
+            <PartiallyUnlockedStake<T>>::insert(stash_account.clone(), unlocking);
+        }
+    } else {
+        <StakingCount<T>>::insert(stash_account.clone(), (num_of_utxos, remaining));
+    }
+
+    match T::SlashPolicy::get() {
+        SlashPolicy::Burn => supply::note_mlt_slashed::<T>(slashed),
+        SlashPolicy::Treasury => {
+            let block_number = <frame_system::Pallet<T>>::block_number();
+            let hash = BlakeTwo256::hash_of(&(stash_account, block_number, "slashed_stake"));
+            if <UtxoStore<T>>::contains_key(hash) {
+                log::warn!("slashed stake utxo {:?} already exists", hash);
+                supply::note_mlt_slashed::<T>(slashed);
+            } else {
+                supply::note_mlt_unlocked::<T>(slashed);
+                let treasury = T::RewardDistribution::get().treasury;
+                <UtxoStore<T>>::insert(hash, TransactionOutput::new_script_hash(slashed, treasury));
+            }
+        }
+    }
+
+    <Pallet<T>>::deposit_event(Event::<T>::StakeSlashed(slashed, stash_account.clone()));
+    slashed
+}
+
 pub mod validation {
     use super::*;
-    use crate::staking::utils::get_all_locked_utxo_outpoints;
     use crate::TransactionOutputFor;
 
-    /// to validate `LockForStaking` and `LockExtraForStaking`
+    /// to validate `LockForStaking`, `LockExtraForStaking` and `LockForNominating`
     pub fn validate_staking_ops<T: Config>(
         tx: &TransactionOutputFor<T>,
         hash_key: H256,
@@ -187,6 +671,7 @@ pub mod validation {
                 stash_account,
                 controller_account,
                 session_key,
+                validator_prefs: _,
             } => {
                 ensure!(
                     tx.value >= T::MinimumStake::get(),
@@ -205,6 +690,16 @@ pub mod validation {
                 ensure!(tx.value > 0, "output value must be nonzero");
                 validate_lock_extra_for_staking_requirements::<T>(stash_account, controller_account)
             }
+            Destination::LockForNominating {
+                stash_account,
+                targets,
+            } => {
+                ensure!(
+                    tx.value >= T::MinimumNominatorStake::get(),
+                    "output value must be equal or more than the minimum nominator stake"
+                );
+                validate_lock_for_nominating_requirements::<T>(stash_account, targets)
+            }
             _non_staking_destinations => {
                 fail!(Error::<T>::InvalidOperation)
             }
@@ -242,6 +737,33 @@ pub mod validation {
         Ok(().into())
     }
 
+    /// Checks whether a transaction is valid to do `lock_for_nominating`.
+    fn validate_lock_for_nominating_requirements<T: Config>(
+        stash_account: &T::AccountId,
+        targets: &Vec<T::AccountId>,
+    ) -> DispatchResultWithPostInfo {
+        // ---------- check for clearance INSIDE the utxo system. ----------
+        ensure!(
+            !<StakingCount<T>>::contains_key(stash_account.clone()),
+            Error::<T>::StashAccountAlreadyRegistered
+        );
+
+        ensure!(!targets.is_empty(), "no validators to nominate");
+
+        // ---------- check for clearance OUTSIDE the utxo system. ----------
+        ensure!(
+            !T::StakingHelper::is_controller_account_exist(stash_account),
+            "specified stash account is a controller account"
+        );
+
+        ensure!(
+            T::StakingHelper::are_valid_nomination_targets(targets),
+            "nomination targets must be validators, and not more than the maximum allowed"
+        );
+
+        Ok(().into())
+    }
+
     /// Checks whether a transaction is valid to do extra locking of utxos for staking
     fn validate_lock_extra_for_staking_requirements<T: Config>(
         stash_account: &T::AccountId,
@@ -279,7 +801,33 @@ pub mod validation {
 
         let controller_account = T::StakingHelper::get_controller_account(stash_account)?;
 
-        // unlock operation is allowed ONLY for locked funds.
+        // unlock operation is allowed ONLY for locked funds, one request at a time.
+        ensure!(
+            T::StakingHelper::are_funds_locked(&controller_account)
+                && !T::StakingHelper::has_unlocking_funds(&controller_account),
+            Error::<T>::FundsAtUnlockedState
+        );
+
+        Ok(().into())
+    }
+
+    /// Checks whether the stash account is a validator that can change its preferences.
+    pub(crate) fn validate_set_validator_preferences<T: Config>(
+        stash_account: &T::AccountId,
+    ) -> DispatchResultWithPostInfo {
+        ensure!(
+            <StakingCount<T>>::contains_key(stash_account.clone()),
+            Error::<T>::StashAccountNotFound
+        );
+
+        ensure!(
+            !utils::is_nominator::<T>(stash_account),
+            "only validators have preferences"
+        );
+
+        let controller_account = T::StakingHelper::get_controller_account(stash_account)?;
+
+        // a validator that stopped validating has nothing to set.
         ensure!(
             T::StakingHelper::are_funds_locked(&controller_account),
             Error::<T>::FundsAtUnlockedState
@@ -288,6 +836,107 @@ pub mod validation {
         Ok(().into())
     }
 
+    /// Checks whether the validator can replace its session keys.
+    pub(crate) fn validate_rotate_session_keys<T: Config>(
+        stash_account: &T::AccountId,
+        session_key: &Vec<u8>,
+    ) -> DispatchResultWithPostInfo {
+        ensure!(
+            <StakingCount<T>>::contains_key(stash_account.clone()),
+            Error::<T>::StashAccountNotFound
+        );
+
+        ensure!(
+            !utils::is_nominator::<T>(stash_account),
+            "only validators have session keys"
+        );
+
+        ensure!(
+            T::StakingHelper::can_decode_session_key(session_key),
+            "please input a valid session key."
+        );
+
+        Ok(().into())
+    }
+
+    /// Checks whether the validator can use the given account as its new controller.
+    pub(crate) fn validate_change_controller<T: Config>(
+        stash_account: &T::AccountId,
+        controller_account: &T::AccountId,
+    ) -> DispatchResultWithPostInfo {
+        ensure!(
+            <StakingCount<T>>::contains_key(stash_account.clone()),
+            Error::<T>::StashAccountNotFound
+        );
+
+        // a nominator is its own controller.
+        ensure!(
+            !utils::is_nominator::<T>(stash_account),
+            "only validators can change their controller account"
+        );
+
+        ensure!(
+            !<StakingCount<T>>::contains_key(controller_account.clone()),
+            "specified controller account is a stash account"
+        );
+
+        ensure!(
+            !T::StakingHelper::is_controller_account_exist(controller_account),
+            "specified controller account is already used."
+        );
+
+        Ok(().into())
+    }
+
+    /// Checks whether there is an unlocked stake to lock back.
+    pub(crate) fn validate_rebond<T: Config>(
+        stash_account: &T::AccountId,
+    ) -> DispatchResultWithPostInfo {
+        ensure!(
+            <StakingCount<T>>::contains_key(stash_account.clone()),
+            Error::<T>::StashAccountNotFound
+        );
+
+        let controller_account = T::StakingHelper::get_controller_account(stash_account)?;
+
+        ensure!(
+            T::StakingHelper::has_unlocking_funds(&controller_account),
+            "Funds are still locked. There is nothing to rebond."
+        );
+
+        Ok(().into())
+    }
+
+    /// Checks whether unlocking part of the stake is allowed.
+    /// The stake left must still be enough to be a validator, or a nominator.
+    pub(crate) fn validate_partial_unlock_request_for_withdrawal<T: Config>(
+        stash_account: &T::AccountId,
+        value: Value,
+    ) -> DispatchResultWithPostInfo {
+        validate_unlock_request_for_withdrawal::<T>(stash_account)?;
+
+        ensure!(
+            value > T::StakeWithdrawalFee::get(),
+            "unlocked value must be more than the withdrawal fee"
+        );
+
+        let (_, total) = <StakingCount<T>>::get(stash_account.clone())
+            .ok_or("cannot find the stash account inside the StakingCount storage")?;
+        let remaining = total.checked_sub(value).ok_or("cannot unlock more than the total stake")?;
+
+        let minimum_stake = if utils::is_nominator::<T>(stash_account) {
+            T::MinimumNominatorStake::get()
+        } else {
+            T::MinimumStake::get()
+        };
+        ensure!(
+            remaining >= minimum_stake,
+            "remaining stake must be equal or more than the minimum stake"
+        );
+
+        Ok(().into())
+    }
+
     /// It includes:
     /// 1. Check if the pub key is a controller.
     /// 2. Checking the number of outpoints owned by the given pub key
@@ -315,7 +964,7 @@ pub mod validation {
         // if the funds are already unlocked, it means you should withdraw them first,
         // then perform a `LockForStaking`.
         ensure!(
-            !T::StakingHelper::are_funds_locked(&controller_account),
+            T::StakingHelper::has_unlocking_funds(&controller_account),
             "Funds are still locked. Perform `unlock_request_for_withdrawal` first."
         );
 
@@ -333,7 +982,6 @@ pub mod validation {
 
 mod utils {
     use super::*;
-    use sp_runtime::DispatchError;
 
     /// Retrieves all the outpoints owned by the given stash acount.
     // TODO: keep track of "our" Locked UTXO separately?
@@ -344,10 +992,15 @@ mod utils {
                     stash_account,
                     controller_account: _,
                     session_key: _,
+                    validator_prefs: _,
                 }
                 | Destination::LockExtraForStaking {
                     stash_account,
                     controller_account: _,
+                }
+                | Destination::LockForNominating {
+                    stash_account,
+                    targets: _,
                 } => {
                     if *stash_acc == stash_account {
                         Some(k)
@@ -360,6 +1013,30 @@ mod utils {
             .collect()
     }
 
+    /// Checks whether the stash account locked its utxos to nominate, instead of validating.
+    pub fn is_nominator<T: Config>(stash_acc: &T::AccountId) -> bool {
+        LockedUtxos::<T>::iter_values().any(|utxo| match utxo.destination {
+            Destination::LockForNominating { stash_account, .. } => *stash_acc == stash_account,
+            _ => false,
+        })
+    }
+
+    /// Retrieves the destination of the first stake of the stash account,
+    /// which holds the details of its validating or nominating.
+    pub fn get_stake_destination<T: Config>(
+        stash_acc: &T::AccountId,
+    ) -> Option<Destination<T::AccountId>> {
+        LockedUtxos::<T>::iter_values()
+            .map(|utxo| utxo.destination)
+            .find(|destination| match destination {
+                Destination::LockForStaking { stash_account, .. }
+                | Destination::LockForNominating { stash_account, .. } => {
+                    stash_acc == stash_account
+                }
+                _ => false,
+            })
+    }
+
     /// removes all locked utxos of the given stash_account.
     /// returns the list of outpoints removed from the `LockedUtxo` storage
     pub fn remove_locked_utxos<T: Config>(stash_account: &T::AccountId) -> Vec<H256> {
@@ -398,6 +1075,7 @@ mod utils {
             hash_key
         );
         <LockedUtxos<T>>::insert(hash_key, output);
+        supply::note_mlt_locked::<T>(output.value);
 
         Ok(().into())
     }
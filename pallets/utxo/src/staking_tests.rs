@@ -16,12 +16,17 @@
 // Author(s): C. Yap
 
 use crate::{
-    mock::*, Destination, Error, LockedUtxos, StakingCount, Transaction, TransactionInput,
-    TransactionOutput, UtxoStore,
+    mock::*, staking::slash_locked_stake, Destination, Error, LockedUtxos, RewardTotal,
+    SlashPolicy, StakingCount, StakingRewardDestination, Transaction, TransactionInput,
+    TransactionOutput, UtxoStore, ValidatorPreferences,
 };
 use codec::Encode;
 use frame_support::{assert_err, assert_ok, sp_io::crypto};
 use sp_core::{sp_std::vec, testing::SR25519, H256};
+use sp_runtime::{
+    traits::{BlakeTwo256, Hash},
+    Perbill,
+};
 
 // JUST FOR SEEKING BUG IN FUNCTIONAL TEST
 // todo: Remove this
@@ -176,6 +181,7 @@ fn non_mlt_staking() {
                         stash_account: H256::from(karl_pub_key),
                         controller_account: H256::from(greg_pub_key),
                         session_key: vec![2, 1],
+                        validator_prefs: Default::default(),
                     },
                     data: Some(OutputData::TokenIssuanceV1 {
                         token_ticker: "Token".as_bytes().to_vec(),
@@ -401,4 +407,557 @@ fn withdrawing_before_expected_period() {
     })
 }
 
+#[test]
+fn partial_unlocking_and_withdrawing() {
+    let (mut test_ext, keys_and_hashes) = multiple_keys_test_ext();
+    test_ext.execute_with(|| {
+        let (alice_pub_key, alice_genesis) = keys_and_hashes[0];
+        let (tom_pub_key, _) = keys_and_hashes[3];
+        let alice_h256 = H256::from(alice_pub_key);
+
+        // ALICE (index 0) adds 20 to her stake of 10.
+        let utxo = UtxoStore::<Test>::get(alice_genesis).expect("alice's utxo does not exist");
+        let tx = Transaction {
+            inputs: vec![TransactionInput::new_empty(alice_genesis)],
+            outputs: vec![TransactionOutput::new_lock_extra_for_staking(
+                20,
+                alice_h256,
+                H256::from(tom_pub_key),
+            )],
+            time_lock: Default::default(),
+        }
+        .sign(&[utxo], 0, &alice_pub_key)
+        .expect("alice's pub key not found");
+        assert_ok!(Utxo::spend(Origin::signed(H256::zero()), tx));
+        assert_eq!(StakingCount::<Test>::get(alice_h256), Some((2, 30)));
+        let reward_total = RewardTotal::<Test>::get();
+
+        // the remaining stake cannot be less than the minimum stake.
+        assert_err!(
+            Utxo::partial_unlock_request_for_withdrawal(Origin::signed(alice_h256), 25),
+            "remaining stake must be equal or more than the minimum stake"
+        );
+        assert_err!(
+            Utxo::partial_unlock_request_for_withdrawal(Origin::signed(alice_h256), 1),
+            "unlocked value must be more than the withdrawal fee"
+        );
+
+        assert_ok!(Utxo::partial_unlock_request_for_withdrawal(
+            Origin::signed(alice_h256),
+            15
+        ));
+
+        // only one unlock request at a time.
+        assert_err!(
+            Utxo::partial_unlock_request_for_withdrawal(Origin::signed(alice_h256), 5),
+            Error::<Test>::FundsAtUnlockedState
+        );
+
+        // ALICE is still validating with the rest of her stake.
+        assert_ok!(Utxo::set_validator_preferences(
+            Origin::signed(alice_h256),
+            ValidatorPreferences::default()
+        ));
+
+        assert_err!(
+            Utxo::withdraw_stake(Origin::signed(alice_h256)),
+            "not yet time to withdraw"
+        );
+
+        // increase the block number 6 times, as if new blocks has been created.
+        for _ in 1..6 {
+            next_block();
+        }
+        assert_ok!(Utxo::withdraw_stake(Origin::signed(alice_h256)));
+
+        // ALICE gets back 15, minus the withdrawal fee of 1.
+        assert!(UtxoStore::<Test>::iter_values()
+            .any(|utxo| utxo == TransactionOutput::new_pubkey(14, alice_h256)));
+        assert_eq!(RewardTotal::<Test>::get(), reward_total + 1);
+
+        // the rest of the stake remains locked in one utxo, with its first stake details.
+        assert_eq!(StakingCount::<Test>::get(alice_h256), Some((1, 15)));
+        let locked: Vec<TransactionOutput<H256>> = LockedUtxos::<Test>::iter_values().collect();
+        assert_eq!(
+            locked,
+            vec![TransactionOutput::new_lock_for_staking(
+                15,
+                alice_h256,
+                H256::from(tom_pub_key),
+                vec![3, 1],
+            )]
+        );
+        assert_eq!(Utxo::partially_unlocked_stake(alice_h256), None);
+
+        // ALICE can still unlock all of it.
+        assert_ok!(Utxo::unlock_request_for_withdrawal(Origin::signed(
+            alice_h256
+        )));
+    })
+}
+
+#[test]
+fn partial_withdrawal_to_an_existing_outpoint() {
+    let (mut test_ext, keys_and_hashes) = multiple_keys_test_ext();
+    test_ext.execute_with(|| {
+        let (alice_pub_key, alice_genesis) = keys_and_hashes[0];
+        let (tom_pub_key, _) = keys_and_hashes[3];
+        let alice_h256 = H256::from(alice_pub_key);
+
+        // ALICE (index 0) adds 20 to her stake of 10, and unlocks 15 of it.
+        let utxo = UtxoStore::<Test>::get(alice_genesis).expect("alice's utxo does not exist");
+        let tx = Transaction {
+            inputs: vec![TransactionInput::new_empty(alice_genesis)],
+            outputs: vec![TransactionOutput::new_lock_extra_for_staking(
+                20,
+                alice_h256,
+                H256::from(tom_pub_key),
+            )],
+            time_lock: Default::default(),
+        }
+        .sign(&[utxo], 0, &alice_pub_key)
+        .expect("alice's pub key not found");
+        assert_ok!(Utxo::spend(Origin::signed(H256::zero()), tx));
+        assert_ok!(Utxo::partial_unlock_request_for_withdrawal(
+            Origin::signed(alice_h256),
+            15
+        ));
+        for _ in 1..6 {
+            next_block();
+        }
+
+        // the withdrawn utxo would overwrite the one already at its outpoint.
+        let outpoints: Vec<H256> = LockedUtxos::<Test>::iter()
+            .filter_map(|(outpoint, utxo)| match utxo.destination {
+                Destination::LockForStaking { stash_account, .. }
+                | Destination::LockExtraForStaking { stash_account, .. }
+                    if stash_account == alice_h256 =>
+                {
+                    Some(outpoint)
+                }
+                _ => None,
+            })
+            .collect();
+        let existing = TransactionOutput::new_pubkey(7, alice_h256);
+        UtxoStore::<Test>::insert(BlakeTwo256::hash_of(&outpoints), existing.clone());
+
+        assert_err!(
+            Utxo::withdraw_stake(Origin::signed(alice_h256)),
+            Error::<Test>::OutpointAlreadyExists
+        );
+        assert_eq!(
+            UtxoStore::<Test>::get(BlakeTwo256::hash_of(&outpoints)),
+            Some(existing)
+        );
+        assert_eq!(Utxo::partially_unlocked_stake(alice_h256), Some(15));
+        assert_eq!(StakingCount::<Test>::get(alice_h256), Some((2, 30)));
+    })
+}
+
+#[test]
+fn nominating_and_withdrawing() {
+    let (mut test_ext, keys_and_hashes) = multiple_keys_test_ext();
+    test_ext.execute_with(|| {
+        let (karl_pub_key, karl_genesis) = keys_and_hashes[1];
+        let alice_h256 = H256::from(keys_and_hashes[0].0);
+        let karl_h256 = H256::from(karl_pub_key);
+        let greg_h256 = H256::from(keys_and_hashes[2].0);
+        let utxo = UtxoStore::<Test>::get(karl_genesis).expect("karl's utxo does not exist");
+
+        let nominate = |value, targets| {
+            Transaction {
+                inputs: vec![TransactionInput::new_empty(karl_genesis)],
+                outputs: vec![
+                    TransactionOutput::new_lock_for_nominating(value, karl_h256, targets),
+                    TransactionOutput::new_pubkey(100 - value, karl_h256),
+                ],
+                time_lock: Default::default(),
+            }
+            .sign(&[utxo.clone()], 0, &karl_pub_key)
+            .expect("karl's pub key not found")
+        };
+
+        // minimum value to nominate is 5.
+        assert_err!(
+            Utxo::spend(Origin::signed(H256::zero()), nominate(4, vec![alice_h256])),
+            "output value must be equal or more than the minimum nominator stake"
+        );
+        // GREG is not a validator.
+        assert_err!(
+            Utxo::spend(Origin::signed(H256::zero()), nominate(8, vec![greg_h256])),
+            "nomination targets must be validators, and not more than the maximum allowed"
+        );
+        assert_err!(
+            Utxo::spend(Origin::signed(H256::zero()), nominate(8, vec![])),
+            "no validators to nominate"
+        );
+
+        // KARL (index 1) nominates ALICE with 8, less than the minimum stake of a validator.
+        let tx = nominate(8, vec![alice_h256]);
+        let locked_utxo_hash = tx.outpoint(0);
+        assert_ok!(Utxo::spend(Origin::signed(H256::zero()), tx));
+        assert!(LockedUtxos::<Test>::contains_key(locked_utxo_hash));
+        assert_eq!(StakingCount::<Test>::get(karl_h256), Some((1, 8)));
+
+        // the remaining stake of a nominator can go down to the minimum nominator stake.
+        assert_err!(
+            Utxo::partial_unlock_request_for_withdrawal(Origin::signed(karl_h256), 4),
+            "remaining stake must be equal or more than the minimum stake"
+        );
+
+        assert_ok!(Utxo::unlock_request_for_withdrawal(Origin::signed(
+            karl_h256
+        )));
+
+        // increase the block number 6 times, as if new blocks has been created.
+        for _ in 1..6 {
+            next_block();
+        }
+        assert_ok!(Utxo::withdraw_stake(Origin::signed(karl_h256)));
+
+        // KARL gets back 8, minus the withdrawal fee of 1.
+        assert!(!LockedUtxos::<Test>::contains_key(locked_utxo_hash));
+        assert!(!StakingCount::<Test>::contains_key(karl_h256));
+        assert!(UtxoStore::<Test>::iter_values()
+            .any(|utxo| utxo == TransactionOutput::new_pubkey(7, karl_h256)));
+    })
+}
+
+#[test]
+fn setting_validator_preferences() {
+    let (mut test_ext, keys_and_hashes) = multiple_keys_test_ext();
+    test_ext.execute_with(|| {
+        System::set_block_number(1);
+        let alice_h256 = H256::from(keys_and_hashes[0].0);
+        let karl_h256 = H256::from(keys_and_hashes[1].0);
+        let tom_h256 = H256::from(keys_and_hashes[3].0);
+        let prefs = ValidatorPreferences {
+            commission: Perbill::from_percent(10),
+            blocked: true,
+        };
+
+        assert_err!(
+            Utxo::set_validator_preferences(Origin::signed(karl_h256), prefs.clone()),
+            Error::<Test>::StashAccountNotFound
+        );
+
+        assert_ok!(Utxo::set_validator_preferences(
+            Origin::signed(alice_h256),
+            prefs.clone()
+        ));
+        assert!(System::events().iter().any(|record| record.event
+            == Event::Utxo(crate::Event::ValidatorPreferencesChanged(
+                alice_h256,
+                prefs.clone()
+            ))));
+
+        // the preferences are kept in ALICE's stake.
+        let locked: Vec<TransactionOutput<H256>> = LockedUtxos::<Test>::iter_values().collect();
+        assert_eq!(
+            locked,
+            vec![TransactionOutput::new_lock_for_staking_with_prefs(
+                10,
+                alice_h256,
+                tom_h256,
+                vec![3, 1],
+                prefs.clone(),
+            )]
+        );
+
+        // no more preferences once ALICE stops validating.
+        assert_ok!(Utxo::unlock_request_for_withdrawal(Origin::signed(
+            alice_h256
+        )));
+        assert_err!(
+            Utxo::set_validator_preferences(Origin::signed(alice_h256), prefs),
+            Error::<Test>::FundsAtUnlockedState
+        );
+    })
+}
+
+#[test]
+fn rotating_session_keys_and_changing_controller() {
+    let (mut test_ext, keys_and_hashes) = multiple_keys_test_ext();
+    test_ext.execute_with(|| {
+        System::set_block_number(1);
+        let alice_h256 = H256::from(keys_and_hashes[0].0);
+        let karl_h256 = H256::from(keys_and_hashes[1].0);
+        let greg_h256 = H256::from(keys_and_hashes[2].0);
+        let tom_h256 = H256::from(keys_and_hashes[3].0);
+
+        assert_err!(
+            Utxo::rotate_session_keys(Origin::signed(karl_h256), vec![4, 1]),
+            Error::<Test>::StashAccountNotFound
+        );
+        assert_ok!(Utxo::rotate_session_keys(
+            Origin::signed(alice_h256),
+            vec![4, 1]
+        ));
+        assert!(System::events().iter().any(|record| record.event
+            == Event::Utxo(crate::Event::SessionKeysRotated(alice_h256))));
+
+        // the stash account itself cannot be the new controller.
+        assert_err!(
+            Utxo::change_controller(Origin::signed(alice_h256), alice_h256),
+            "specified controller account is a stash account"
+        );
+        // TOM is already the controller.
+        assert_err!(
+            Utxo::change_controller(Origin::signed(alice_h256), tom_h256),
+            "specified controller account is already used."
+        );
+        assert_ok!(Utxo::change_controller(
+            Origin::signed(alice_h256),
+            greg_h256
+        ));
+        assert!(System::events().iter().any(|record| record.event
+            == Event::Utxo(crate::Event::ControllerChanged(alice_h256, greg_h256))));
+
+        // ALICE's stake keeps the new details.
+        let locked: Vec<TransactionOutput<H256>> = LockedUtxos::<Test>::iter_values().collect();
+        assert_eq!(
+            locked,
+            vec![TransactionOutput::new_lock_for_staking(
+                10,
+                alice_h256,
+                greg_h256,
+                vec![4, 1],
+            )]
+        );
+
+        // locking extra funds now needs the new controller.
+        let (alice_pub_key, alice_genesis) = keys_and_hashes[0];
+        let utxo = UtxoStore::<Test>::get(alice_genesis).expect("alice's utxo does not exist");
+        let lock_extra = |controller| {
+            Transaction {
+                inputs: vec![TransactionInput::new_empty(alice_genesis)],
+                outputs: vec![TransactionOutput::new_lock_extra_for_staking(
+                    20, alice_h256, controller,
+                )],
+                time_lock: Default::default(),
+            }
+            .sign(&[utxo.clone()], 0, &alice_pub_key)
+            .expect("alice's pub key not found")
+        };
+        // TOM is not a controller anymore.
+        assert!(Utxo::spend(Origin::signed(H256::zero()), lock_extra(tom_h256)).is_err());
+        assert_ok!(Utxo::spend(
+            Origin::signed(H256::zero()),
+            lock_extra(greg_h256)
+        ));
+    })
+}
+
+#[test]
+fn rebonding_unlocked_stake() {
+    let (mut test_ext, keys_and_hashes) = multiple_keys_test_ext();
+    test_ext.execute_with(|| {
+        System::set_block_number(1);
+        let (alice_pub_key, alice_genesis) = keys_and_hashes[0];
+        let alice_h256 = H256::from(alice_pub_key);
+        let tom_h256 = H256::from(keys_and_hashes[3].0);
+
+        assert_err!(
+            Utxo::rebond(Origin::signed(alice_h256)),
+            "Funds are still locked. There is nothing to rebond."
+        );
+
+        // ALICE (index 0) changes her mind after unlocking her whole stake.
+        assert_ok!(Utxo::unlock_request_for_withdrawal(Origin::signed(
+            alice_h256
+        )));
+        assert_ok!(Utxo::rebond(Origin::signed(alice_h256)));
+        assert!(System::events().iter().any(|record| record.event
+            == Event::Utxo(crate::Event::StakeRebonded(alice_h256))));
+        assert_eq!(StakingCount::<Test>::get(alice_h256), Some((1, 10)));
+
+        // nothing to withdraw, even after the withdrawal period.
+        for _ in 1..6 {
+            next_block();
+        }
+        assert_err!(
+            Utxo::withdraw_stake(Origin::signed(alice_h256)),
+            "Funds are still locked. Perform `unlock_request_for_withdrawal` first."
+        );
+
+        // ALICE adds 20 to her stake, and then unlocks part of it.
+        let utxo = UtxoStore::<Test>::get(alice_genesis).expect("alice's utxo does not exist");
+        let tx = Transaction {
+            inputs: vec![TransactionInput::new_empty(alice_genesis)],
+            outputs: vec![TransactionOutput::new_lock_extra_for_staking(
+                20, alice_h256, tom_h256,
+            )],
+            time_lock: Default::default(),
+        }
+        .sign(&[utxo], 0, &alice_pub_key)
+        .expect("alice's pub key not found");
+        assert_ok!(Utxo::spend(Origin::signed(H256::zero()), tx));
+        assert_ok!(Utxo::partial_unlock_request_for_withdrawal(
+            Origin::signed(alice_h256),
+            15
+        ));
+        assert_ok!(Utxo::rebond(Origin::signed(alice_h256)));
+        assert_eq!(Utxo::partially_unlocked_stake(alice_h256), None);
+        assert_eq!(StakingCount::<Test>::get(alice_h256), Some((2, 30)));
+    })
+}
+
+#[test]
+fn slashing_and_withdrawing() {
+    let (mut test_ext, keys_and_hashes) = multiple_keys_test_ext();
+    test_ext.execute_with(|| {
+        System::set_block_number(1);
+        let alice_h256 = H256::from(keys_and_hashes[0].0);
+        let karl_h256 = H256::from(keys_and_hashes[1].0);
+        let supply = Utxo::mlt_supply();
+
+        // only stakers can be slashed.
+        assert_eq!(slash_locked_stake::<Test>(&karl_h256, 4), 0);
+
+        // ALICE (index 0) is slashed 4 out of her stake of 10.
+        assert_eq!(slash_locked_stake::<Test>(&alice_h256, 4), 4);
+        assert_eq!(StakingCount::<Test>::get(alice_h256), Some((1, 6)));
+        assert!(LockedUtxos::<Test>::iter_values().all(|utxo| utxo.value == 6));
+        assert!(System::events().iter().any(|record| record.event
+            == Event::Utxo(crate::Event::StakeSlashed(4, alice_h256))));
+        assert_eq!(Utxo::mlt_supply().locked, supply.locked - 4);
+        assert_eq!(Utxo::mlt_supply().burned, supply.burned + 4);
+
+        assert_ok!(Utxo::unlock_request_for_withdrawal(Origin::signed(
+            alice_h256
+        )));
+        for _ in 1..6 {
+            next_block();
+        }
+        assert_ok!(Utxo::withdraw_stake(Origin::signed(alice_h256)));
+
+        // ALICE only gets back what is left, minus the withdrawal fee of 1.
+        assert!(UtxoStore::<Test>::iter_values()
+            .any(|utxo| utxo == TransactionOutput::new_pubkey(5, alice_h256)));
+        assert!(!UtxoStore::<Test>::iter_values()
+            .any(|utxo| utxo == TransactionOutput::new_pubkey(9, alice_h256)));
+    })
+}
+
+#[test]
+fn slashing_the_whole_stake() {
+    let (mut test_ext, keys_and_hashes) = multiple_keys_test_ext();
+    test_ext.execute_with(|| {
+        let alice_h256 = H256::from(keys_and_hashes[0].0);
+        let supply = Utxo::mlt_supply();
+        let reward_total = RewardTotal::<Test>::get();
+
+        // ALICE is left with 1 out of her stake of 10, only enough for the withdrawal fee.
+        assert_eq!(slash_locked_stake::<Test>(&alice_h256, 9), 9);
+        assert_eq!(StakingCount::<Test>::get(alice_h256), None);
+        assert_eq!(LockedUtxos::<Test>::iter().count(), 0);
+        assert_eq!(RewardTotal::<Test>::get(), reward_total + 1);
+        assert_eq!(Utxo::mlt_supply().locked, supply.locked - 10);
+        assert_eq!(Utxo::mlt_supply().burned, supply.burned + 9);
+
+        // ALICE doesn't validate, nor has a bond, anymore.
+        MOCK_STAKING.with(|stake_info| {
+            let stake_info = stake_info.borrow();
+            assert!(!stake_info.lock_map.contains_key(&alice_h256));
+            assert!(!stake_info.lock_ctrl_map.contains_key(&alice_h256));
+            assert!(stake_info.ctrl_map.is_empty());
+        });
+
+        // there is nothing left to slash, or to withdraw.
+        assert_eq!(slash_locked_stake::<Test>(&alice_h256, 1), 0);
+        assert_err!(
+            Utxo::withdraw_stake(Origin::signed(alice_h256)),
+            "StashAccountNotFound"
+        );
+    })
+}
+
+#[test]
+fn slashing_to_the_treasury() {
+    let (mut test_ext, keys_and_hashes) = multiple_keys_test_ext();
+    test_ext.execute_with(|| {
+        StakeSlashPolicy::set(&SlashPolicy::Treasury);
+        let alice_h256 = H256::from(keys_and_hashes[0].0);
+        let supply = Utxo::mlt_supply();
+
+        // the slash cannot be more than the stake.
+        assert_eq!(slash_locked_stake::<Test>(&alice_h256, 15), 10);
+        assert_eq!(StakingCount::<Test>::get(alice_h256), None);
+
+        let treasury = BlockRewardDistribution::get().treasury;
+        assert!(UtxoStore::<Test>::iter_values()
+            .any(|utxo| utxo == TransactionOutput::new_script_hash(10, treasury)));
+        assert_eq!(Utxo::mlt_supply().circulating, supply.circulating + 10);
+        assert_eq!(Utxo::mlt_supply().burned, supply.burned);
+    })
+}
+
+#[test]
+fn era_rewards_payout() {
+    let (mut test_ext, keys_and_hashes) = multiple_keys_test_ext();
+    test_ext.execute_with(|| {
+        System::set_block_number(1);
+        let alice_h256 = H256::from(keys_and_hashes[0].0);
+        let karl_h256 = H256::from(keys_and_hashes[1].0);
+        let script_hash = H256::repeat_byte(7);
+        let issued = Utxo::mlt_supply().issued;
+
+        // only stakers can choose where their era rewards go.
+        assert_err!(
+            Utxo::set_staking_reward_destination(
+                Origin::signed(karl_h256),
+                Destination::ScriptHash(script_hash)
+            ),
+            Error::<Test>::StashAccountNotFound
+        );
+        assert_err!(
+            Utxo::set_staking_reward_destination(
+                Origin::signed(alice_h256),
+                Destination::CreatePP(vec![], vec![], 0)
+            ),
+            "era rewards can only be sent to a pubkey or a script hash"
+        );
+        assert_ok!(Utxo::set_staking_reward_destination(
+            Origin::signed(alice_h256),
+            Destination::ScriptHash(script_hash)
+        ));
+
+        // ALICE validated era 1, with KARL nominating her.
+        set_era_rewards(alice_h256, 1, vec![(alice_h256, 50), (karl_h256, 20)]);
+        assert_ok!(Utxo::payout_stakers(
+            Origin::signed(H256::zero()),
+            alice_h256,
+            1
+        ));
+
+        let alice_reward = TransactionOutput {
+            value: 50,
+            destination: Destination::ScriptHash(script_hash),
+            data: None,
+        };
+        let karl_reward = TransactionOutput::new_pubkey(20, karl_h256);
+        assert!(UtxoStore::<Test>::iter_values().any(|utxo| utxo == alice_reward));
+        assert!(UtxoStore::<Test>::iter_values().any(|utxo| utxo == karl_reward));
+        assert!(System::events().iter().any(|record| record.event
+            == Event::Utxo(crate::Event::EraRewardPaid(karl_reward.clone(), karl_h256, 1))));
+        assert_eq!(Utxo::mlt_supply().issued, issued + 70);
+
+        // an era can only be paid out once.
+        assert_err!(
+            Utxo::payout_stakers(Origin::signed(H256::zero()), alice_h256, 1),
+            "AlreadyClaimed"
+        );
+
+        // the reward destination goes away with the stake.
+        assert_ok!(Utxo::unlock_request_for_withdrawal(Origin::signed(
+            alice_h256
+        )));
+        for _ in 1..6 {
+            next_block();
+        }
+        assert_ok!(Utxo::withdraw_stake(Origin::signed(alice_h256)));
+        assert!(!StakingRewardDestination::<Test>::contains_key(alice_h256));
+    })
+}
+
 //TODO: add more test scenarios
@@ -0,0 +1,256 @@
+// Copyright (c) 2021 RBB S.r.l
+// opensource@mintlayer.org
+// SPDX-License-Identifier: MIT
+// Licensed under the MIT License;
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// 	http://spdx.org/licenses/MIT
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::{
+    tokens::{OutputData, TokenId, Value},
+    Config, MltSupply, TokenIssuanceId, TokenSupply, TransactionFor, UtxoStore,
+};
+use codec::{Decode, Encode};
+use frame_support::RuntimeDebug;
+#[cfg(feature = "std")]
+use serde::{Deserialize, Serialize};
+use sp_core::sp_std::collections::btree_map::BTreeMap;
+
+/// Keeps count of how much of a currency exists in the chain.
+/// `issued` is always equal to the sum of `circulating`, `locked` and `burned`.
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
+#[derive(Clone, Encode, Decode, Eq, PartialEq, RuntimeDebug, Default)]
+pub struct Supply {
+    /// everything ever created, by genesis, block rewards or token issuance.
+    pub issued: Value,
+    /// the spendable amount, including the fees waiting in the `RewardTotal`.
+    pub circulating: Value,
+    /// the amount locked for staking.
+    pub locked: Value,
+    /// the amount that can never be spent again.
+    pub burned: Value,
+}
+
+impl Supply {
+    fn issue(&mut self, amount: Value) {
+        self.issued = self.issued.saturating_add(amount);
+        self.circulating = self.circulating.saturating_add(amount);
+    }
+
+    fn lock(&mut self, amount: Value) {
+        self.circulating = self.circulating.saturating_sub(amount);
+        self.locked = self.locked.saturating_add(amount);
+    }
+
+    fn unlock(&mut self, amount: Value) {
+        self.locked = self.locked.saturating_sub(amount);
+        self.circulating = self.circulating.saturating_add(amount);
+    }
+
+    fn burn(&mut self, amount: Value) {
+        self.circulating = self.circulating.saturating_sub(amount);
+        self.burned = self.burned.saturating_add(amount);
+    }
+
+    fn burn_locked(&mut self, amount: Value) {
+        self.locked = self.locked.saturating_sub(amount);
+        self.burned = self.burned.saturating_add(amount);
+    }
+}
+
+/// Newly created MLT coins, from genesis or from block rewards.
+pub(crate) fn note_mlt_issued<T: Config>(amount: Value) {
+    <MltSupply<T>>::mutate(|supply| supply.issue(amount));
+}
+
+/// MLT coins moved to the `LockedUtxos`.
+pub(crate) fn note_mlt_locked<T: Config>(amount: Value) {
+    <MltSupply<T>>::mutate(|supply| supply.lock(amount));
+}
+
+/// MLT coins moved back from the `LockedUtxos`.
+pub(crate) fn note_mlt_unlocked<T: Config>(amount: Value) {
+    <MltSupply<T>>::mutate(|supply| supply.unlock(amount));
+}
+
+/// MLT coins that were lost and can never be spent.
+pub(crate) fn note_mlt_burned<T: Config>(amount: Value) {
+    <MltSupply<T>>::mutate(|supply| supply.burn(amount));
+}
+
+/// MLT coins slashed from the `LockedUtxos`, that can never be spent.
+pub(crate) fn note_mlt_slashed<T: Config>(amount: Value) {
+    <MltSupply<T>>::mutate(|supply| supply.burn_locked(amount));
+}
+
+/// Records the tokens issued and burned by the transaction.
+/// Tokens found in the inputs but not in the outputs are burned.
+/// Make SURE to call this before the spent utxos are removed from the `UtxoStore`.
+pub(crate) fn note_token_transaction<T: Config>(tx: &TransactionFor<T>) {
+    let mut spent: BTreeMap<TokenId, Value> = BTreeMap::new();
+    for input in &tx.inputs {
+        let (token_id, amount) = match <UtxoStore<T>>::get(&input.outpoint).and_then(|u| u.data) {
+            Some(OutputData::TokenTransferV1 { token_id, amount }) => (token_id, amount),
+            Some(OutputData::TokenIssuanceV1 {
+                amount_to_issue, ..
+            }) => match <TokenIssuanceId<T>>::get(input.outpoint) {
+                Some(token_id) => (token_id, amount_to_issue),
+                None => continue,
+            },
+            None => continue,
+        };
+        let total = spent.entry(token_id).or_insert(0);
+        *total = total.saturating_add(amount);
+    }
+
+    for output in &tx.outputs {
+        match &output.data {
+            Some(OutputData::TokenTransferV1 { token_id, amount }) => {
+                if let Some(total) = spent.get_mut(token_id) {
+                    *total = total.saturating_sub(*amount);
+                }
+            }
+            Some(OutputData::TokenIssuanceV1 {
+                amount_to_issue, ..
+            }) => {
+                let token_id = TokenId::new(&tx.inputs[0]);
+                <TokenSupply<T>>::mutate(token_id, |supply| {
+                    supply.get_or_insert_with(Supply::default).issue(*amount_to_issue)
+                });
+            }
+            None => {}
+        }
+    }
+
+    for (token_id, burned) in spent {
+        if burned > 0 {
+            <TokenSupply<T>>::mutate(token_id, |supply| {
+                supply.get_or_insert_with(Supply::default).burn(burned)
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::mock::*;
+    use crate::rewards::reward_block_author;
+    use crate::{BlockAuthor, RewardTotal, Transaction, TransactionInput, TransactionOutput};
+    use frame_support::{assert_ok, sp_io::crypto};
+    use sp_core::{sp_std::vec, testing::SR25519, H256};
+
+    #[test]
+    fn genesis_supply_test() {
+        let (mut test_ext, _) = multiple_keys_test_ext();
+        test_ext.execute_with(|| {
+            // 460 in genesis utxos, and 10 locked by alice.
+            assert_eq!(
+                Utxo::mlt_supply(),
+                Supply {
+                    issued: 470,
+                    circulating: 460,
+                    locked: 10,
+                    burned: 0,
+                }
+            );
+        });
+    }
+
+    #[test]
+    fn reward_supply_test() {
+        let (mut test_ext, keys_and_hashes) = multiple_keys_test_ext();
+        test_ext.execute_with(|| {
+            <RewardTotal<Test>>::put(20);
+            <BlockAuthor<Test>>::put(H256::from(keys_and_hashes[2].0));
+            reward_block_author::<Test>(1);
+
+            // at Block 1, 100 is issued. The fees were already circulating.
+            assert_eq!(
+                Utxo::mlt_supply(),
+                Supply {
+                    issued: 570,
+                    circulating: 560,
+                    locked: 10,
+                    burned: 0,
+                }
+            );
+        });
+    }
+
+    #[test]
+    fn token_supply_test() {
+        alice_test_ext().execute_with(|| {
+            let alice_pub_key = crypto::sr25519_public_keys(SR25519)[0];
+            let alice_h256 = H256::from(alice_pub_key);
+            let (utxo0, hash0) = genesis_utxo();
+
+            let tx = Transaction {
+                inputs: vec![TransactionInput::new_empty(hash0)],
+                outputs: vec![
+                    TransactionOutput::new_pubkey(ALICE_GENESIS_BALANCE - 90, alice_h256),
+                    TransactionOutput::new_p2pk_with_data(
+                        90,
+                        alice_h256,
+                        OutputData::TokenIssuanceV1 {
+                            token_ticker: "BensT".as_bytes().to_vec(),
+                            amount_to_issue: 1_000_000_000,
+                            number_of_decimals: 2,
+                            metadata_uri: "mintlayer.org".as_bytes().to_vec(),
+                        },
+                    ),
+                ],
+                time_lock: Default::default(),
+            }
+            .sign(&[utxo0], 0, &alice_pub_key)
+            .expect("alice's pub key not found");
+            let token_id = TokenId::new(&tx.inputs[0]);
+            assert_ok!(Utxo::spend(Origin::signed(H256::zero()), tx.clone()));
+
+            let issued = Supply {
+                issued: 1_000_000_000,
+                circulating: 1_000_000_000,
+                locked: 0,
+                burned: 0,
+            };
+            assert_eq!(Utxo::token_supply(&token_id), Some(issued));
+
+            // drop all the tokens.
+            let tx = Transaction {
+                inputs: vec![TransactionInput::new_empty(tx.outpoint(1))],
+                outputs: vec![TransactionOutput::new_pubkey(80, alice_h256)],
+                time_lock: Default::default(),
+            }
+            .sign(&[tx.outputs[1].clone()], 0, &alice_pub_key)
+            .expect("alice's pub key not found");
+            assert_ok!(Utxo::spend(Origin::signed(H256::zero()), tx));
+            assert_eq!(
+                Utxo::token_supply(&token_id),
+                Some(Supply {
+                    issued: 1_000_000_000,
+                    circulating: 0,
+                    locked: 0,
+                    burned: 1_000_000_000,
+                })
+            );
+
+            // only the fees left the circulating MLT, and are waiting in the `RewardTotal`.
+            assert_eq!(
+                Utxo::mlt_supply(),
+                Supply {
+                    issued: ALICE_GENESIS_BALANCE,
+                    circulating: ALICE_GENESIS_BALANCE,
+                    locked: 0,
+                    burned: 0,
+                }
+            );
+        });
+    }
+}
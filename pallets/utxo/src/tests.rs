@@ -16,21 +16,24 @@
 // Author(s): C. Yap
 
 use crate::{
-    mock::*, tokens::Value, BlockTime, Destination, RawBlockTime, RewardTotal, Transaction,
-    TransactionInput, TransactionOutput, UtxoStore,
+    mock::*, tokens::Value, BlockTime, Destination, Error, RawBlockTime, RewardTotal, Transaction,
+    TransactionInput, TransactionOutput, UtxoStore, WeightInfo,
 };
 use chainscript::{opcodes::all as opc, Builder};
-use codec::Encode;
+use codec::{Decode, Encode};
 use frame_support::{
     assert_err, assert_noop, assert_ok,
     sp_io::crypto,
     sp_runtime::traits::{BlakeTwo256, Hash},
+    weights::{GetDispatchInfo, Pays},
 };
 
 use crate::script::test::gen_block_time_real;
 use crate::tokens::OutputData;
+use pp_api::ProgrammablePoolApi;
 use proptest::prelude::*;
-use sp_core::{sp_std::vec, sr25519::Public, testing::SR25519, H256, H512};
+use sp_core::{sp_std::vec, sr25519::Public, testing::SR25519, H160, H256, H512};
+use utxo_api::{Beneficiary, UtxoApi};
 
 fn tx_input_gen_no_signature() -> (TransactionOutput<H256>, TransactionInput) {
     let (utxo, hash) = genesis_utxo();
@@ -1907,3 +1910,714 @@ fn test_burn_tokens() {
 fn test_token_id() {
     // todo: Testing token id - not done yet
 }
+
+#[test]
+fn test_create_and_call_contract() {
+    execute_with_alice(|alice_pub_key| {
+        System::set_block_number(1);
+        let caller = H256::from(alice_pub_key);
+        let code = vec![0x00, 0x61, 0x73, 0x6d];
+        let (utxo0, input0) = tx_input_gen_no_signature();
+        let tx = Transaction {
+            inputs: vec![input0],
+            outputs: vec![
+                TransactionOutput::new_create_pp(10, code.clone(), vec![], 0),
+                TransactionOutput::new_pubkey(ALICE_GENESIS_BALANCE - 20, caller),
+            ],
+            time_lock: Default::default(),
+        }
+        .sign_unchecked(&[utxo0], 0, &alice_pub_key);
+        assert_ok!(Utxo::spend(Origin::signed(caller), tx.clone()));
+
+        // the contract account of the mock pool is derived from the code and the utxo.
+        let contract = BlakeTwo256::hash_of(&(&code, tx.outpoint(0)));
+
+        // the value of the `CreatePP` output funds the new contract.
+        assert_eq!(
+            UtxoStore::<Test>::get(tx.outpoint(0)),
+            Some(TransactionOutput::new_call_pp(
+                10,
+                contract,
+                true,
+                vec![],
+                0
+            ))
+        );
+        let funding = tx.outpoint(0);
+        assert_eq!(
+            MockPool::<Test>::contract_balance(&contract),
+            Some((10, vec![(funding, 10)]))
+        );
+
+        let tx = Transaction {
+            inputs: vec![TransactionInput::new_empty(tx.outpoint(1))],
+            outputs: vec![
+                TransactionOutput::new_call_pp(10, contract, true, vec![0x01], 0),
+                TransactionOutput::new_pubkey(ALICE_GENESIS_BALANCE - 40, caller),
+            ],
+            time_lock: Default::default(),
+        }
+        .sign_unchecked(&[tx.outputs[1].clone()], 0, &alice_pub_key);
+        assert_ok!(Utxo::spend(Origin::signed(caller), tx.clone()));
+        assert_eq!(
+            MockPool::<Test>::contract_balance(&contract),
+            Some((20, vec![(funding, 10), (tx.outpoint(0), 10)]))
+        );
+    })
+}
+
+#[test]
+fn test_failed_contract_reverts_transaction() {
+    execute_with_alice(|alice_pub_key| {
+        let caller = H256::from(alice_pub_key);
+        let (utxo0, input0) = tx_input_gen_no_signature();
+        let supply = Utxo::mlt_supply();
+
+        // the mock pool fails to instantiate contracts without code.
+        let tx = Transaction {
+            inputs: vec![input0.clone()],
+            outputs: vec![
+                TransactionOutput::new_pubkey(ALICE_GENESIS_BALANCE - 20, caller),
+                TransactionOutput::new_create_pp(10, vec![], vec![], 0),
+            ],
+            time_lock: Default::default(),
+        }
+        .sign_unchecked(&[utxo0.clone()], 0, &alice_pub_key);
+        assert_err!(
+            Utxo::spend(Origin::signed(caller), tx.clone()),
+            "Failed to instantiate smart contract"
+        );
+
+        // the mock pool reverts calls with input data starting with 0xff.
+        let contract = H256::repeat_byte(5);
+        let tx = Transaction {
+            inputs: vec![input0],
+            outputs: vec![
+                TransactionOutput::new_pubkey(ALICE_GENESIS_BALANCE - 20, caller),
+                TransactionOutput::new_call_pp(10, contract, true, vec![0xff], 0),
+            ],
+            time_lock: Default::default(),
+        }
+        .sign_unchecked(&[utxo0], 0, &alice_pub_key);
+        assert_err!(
+            Utxo::spend(Origin::signed(caller), tx.clone()),
+            "Smart contract call reverted"
+        );
+
+        // nothing was spent, nor created.
+        assert!(UtxoStore::<Test>::contains_key(tx.inputs[0].outpoint));
+        assert!(!UtxoStore::<Test>::contains_key(tx.outpoint(0)));
+        assert_eq!(RewardTotal::<Test>::get(), 0);
+        assert_eq!(Utxo::mlt_supply(), supply);
+    })
+}
+
+#[test]
+fn test_contract_gas_fee_and_refund() {
+    execute_with_alice(|alice_pub_key| {
+        let caller = H256::from(alice_pub_key);
+        let code = vec![0x00, 0x61, 0x73, 0x6d];
+        let (utxo0, input0) = tx_input_gen_no_signature();
+
+        // 100 gas costs 200 at the mock gas price, but only 150 is paid.
+        let tx = Transaction {
+            inputs: vec![input0.clone()],
+            outputs: vec![
+                TransactionOutput::new_create_pp(10, code.clone(), vec![], 100),
+                TransactionOutput::new_pubkey(ALICE_GENESIS_BALANCE - 160, caller),
+            ],
+            time_lock: Default::default(),
+        }
+        .sign_unchecked(&[utxo0.clone()], 0, &alice_pub_key);
+        // the transaction pool rejects it as well.
+        assert_err!(
+            crate::pallet::validate_transaction::<Test>(&tx),
+            "insufficient fee to pay for the gas"
+        );
+        assert_err!(
+            Utxo::spend(Origin::signed(caller), tx),
+            "insufficient fee to pay for the gas"
+        );
+
+        let tx = Transaction {
+            inputs: vec![input0],
+            outputs: vec![
+                TransactionOutput::new_create_pp(10, code, vec![], 100),
+                TransactionOutput::new_pubkey(ALICE_GENESIS_BALANCE - 260, caller),
+            ],
+            time_lock: Default::default(),
+        }
+        .sign_unchecked(&[utxo0], 0, &alice_pub_key);
+
+        // the gas limit is part of the weight, but only the gas consumed is counted,
+        // and the gas is paid by the fee of the transaction, not by the signer.
+        let spend_weight = <Test as crate::Config>::WeightInfo::spend(3);
+        let dispatch_info = Call::Utxo(crate::Call::spend(tx.clone())).get_dispatch_info();
+        assert_eq!(dispatch_info.weight, spend_weight + 100);
+        assert_eq!(dispatch_info.pays_fee, Pays::No);
+
+        // a relayer submits the transaction of alice.
+        let relayer = H256::repeat_byte(9);
+        let refund_hash = BlakeTwo256::hash_of(&(&tx.outpoint(0), "gas_refund"));
+        UtxoStore::<Test>::insert(refund_hash, TransactionOutput::new_pubkey(1, relayer));
+        assert_err!(
+            Utxo::spend(Origin::signed(relayer), tx.clone()),
+            Error::<Test>::OutpointAlreadyExists
+        );
+        UtxoStore::<Test>::remove(refund_hash);
+
+        let post_info = Utxo::spend(Origin::signed(relayer), tx).unwrap();
+        assert_eq!(post_info.actual_weight, Some(spend_weight + 50));
+
+        // the mock pool consumed 50 gas, the other 50 are refunded to alice, not to the relayer.
+        assert_eq!(
+            UtxoStore::<Test>::get(refund_hash),
+            Some(TransactionOutput::new_pubkey(100, caller))
+        );
+        assert_eq!(RewardTotal::<Test>::get(), 150);
+    })
+}
+
+#[test]
+fn test_contract_spend_with_change() {
+    execute_with_alice(|alice_pub_key| {
+        let caller = H256::from(alice_pub_key);
+        let contract = H256::repeat_byte(5);
+        let (utxo0, input0) = tx_input_gen_no_signature();
+        let tx = Transaction {
+            inputs: vec![input0],
+            outputs: vec![
+                TransactionOutput::new_call_pp(30, contract, true, vec![0x01], 0),
+                TransactionOutput::new_pubkey(ALICE_GENESIS_BALANCE - 40, caller),
+            ],
+            time_lock: Default::default(),
+        }
+        .sign_unchecked(&[utxo0], 0, &alice_pub_key);
+        assert_ok!(Utxo::spend(Origin::signed(caller), tx.clone()));
+
+        // the contract can't send more than its utxos hold.
+        let too_much = vec![TransactionOutput::new_pubkey(31, caller)];
+        assert_err!(
+            crate::spend_contract_utxos::<Test>(&contract, &vec![tx.outpoint(0)], too_much),
+            "Contract doesn't have enough funds"
+        );
+
+        // the contract pays 10 to alice, and gets the remaining 20 back.
+        let payout = TransactionOutput::new_pubkey(10, caller);
+        let (_, new_utxos) = crate::spend_contract_utxos::<Test>(
+            &contract,
+            &vec![tx.outpoint(0)],
+            vec![payout.clone()],
+        )
+        .expect("contract spend failed");
+        let change = TransactionOutput::new_call_pp(20, contract, true, vec![], 0);
+        assert!(!UtxoStore::<Test>::contains_key(tx.outpoint(0)));
+        assert_eq!(new_utxos.len(), 2);
+        assert_eq!(UtxoStore::<Test>::get(new_utxos[0]), Some(payout));
+        assert_eq!(UtxoStore::<Test>::get(new_utxos[1]), Some(change));
+        assert_eq!(RewardTotal::<Test>::get(), 10);
+        assert_ok!(crate::check_contract_balance::<Test>(&contract));
+    })
+}
+
+#[test]
+fn test_contract_balance_follows_spent_utxos() {
+    execute_with_alice(|alice_pub_key| {
+        let caller = H256::from(alice_pub_key);
+        let contract = H256::repeat_byte(5);
+        let (utxo0, input0) = tx_input_gen_no_signature();
+        let tx = Transaction {
+            inputs: vec![input0],
+            outputs: vec![
+                TransactionOutput::new_call_pp(30, contract, true, vec![0x01], 0),
+                TransactionOutput::new_call_pp(5, contract, false, vec![0x01], 0),
+                TransactionOutput::new_pubkey(ALICE_GENESIS_BALANCE - 45, caller),
+            ],
+            time_lock: Default::default(),
+        }
+        .sign_unchecked(&[utxo0], 0, &alice_pub_key);
+        assert_ok!(Utxo::spend(Origin::signed(caller), tx.clone()));
+        assert_ok!(crate::check_contract_balance::<Test>(&contract));
+        assert_eq!(MockPool::<Test>::contract_balance(&contract).unwrap().0, 30);
+
+        // both utxos are spent, and the unfunded 5 comes back as funds in the change.
+        assert_ok!(crate::spend_contract_utxos::<Test>(
+            &contract,
+            &vec![tx.outpoint(0), tx.outpoint(1)],
+            vec![TransactionOutput::new_pubkey(12, caller)]
+        ));
+        assert_ok!(crate::check_contract_balance::<Test>(&contract));
+        let (funds, utxos) = MockPool::<Test>::contract_balance(&contract).unwrap();
+        assert_eq!(funds, 23);
+        assert_eq!(utxos.len(), 1);
+
+        // the change can be spent in turn.
+        assert_ok!(crate::spend_contract_utxos::<Test>(
+            &contract,
+            &vec![utxos[0].0],
+            vec![TransactionOutput::new_pubkey(3, caller)]
+        ));
+        assert_ok!(crate::check_contract_balance::<Test>(&contract));
+        assert_eq!(MockPool::<Test>::contract_balance(&contract).unwrap().0, 20);
+
+        // a contract out of sync with the UtxoStore is detected.
+        MOCK_CONTRACTS.with(|contracts| {
+            contracts.borrow_mut().get_mut(&contract).unwrap().0 = 21;
+        });
+        assert_err!(
+            crate::check_contract_balance::<Test>(&contract),
+            "contract funds don't match its utxos"
+        );
+    })
+}
+
+#[test]
+fn test_only_contract_spends_its_utxos() {
+    execute_with_alice(|alice_pub_key| {
+        let caller = H256::from(alice_pub_key);
+        let contract = H256::repeat_byte(5);
+        let other_contract = H256::repeat_byte(6);
+        let (utxo0, input0) = tx_input_gen_no_signature();
+        let tx = Transaction {
+            inputs: vec![input0],
+            outputs: vec![
+                TransactionOutput::new_call_pp(30, contract, true, vec![0x01], 0),
+                TransactionOutput::new_pubkey(ALICE_GENESIS_BALANCE - 40, caller),
+            ],
+            time_lock: Default::default(),
+        }
+        .sign_unchecked(&[utxo0], 0, &alice_pub_key);
+        assert_ok!(Utxo::spend(Origin::signed(caller), tx.clone()));
+
+        // the old OP_SPEND witness doesn't unlock the contract's funds anymore.
+        let attack = Transaction {
+            inputs: vec![TransactionInput::new_script(
+                tx.outpoint(0),
+                Builder::new().into_script(),
+                Builder::new().push_int(0x1337).into_script(),
+            )],
+            outputs: vec![TransactionOutput::new_pubkey(30, caller)],
+            time_lock: Default::default(),
+        };
+        assert_err!(
+            Utxo::spend(Origin::signed(caller), attack),
+            "only the contract can spend its utxos"
+        );
+
+        // neither does any signature, even from the one who paid the contract.
+        let attack = Transaction {
+            inputs: vec![TransactionInput::new_empty(tx.outpoint(0))],
+            outputs: vec![TransactionOutput::new_pubkey(30, caller)],
+            time_lock: Default::default(),
+        }
+        .sign_unchecked(&[tx.outputs[0].clone()], 0, &alice_pub_key);
+        assert_err!(
+            Utxo::spend(Origin::signed(contract), attack),
+            "only the contract can spend its utxos"
+        );
+
+        // nor another contract.
+        assert_err!(
+            crate::spend_contract_utxos::<Test>(
+                &other_contract,
+                &vec![tx.outpoint(0)],
+                vec![TransactionOutput::new_pubkey(30, caller)]
+            ),
+            "Failed to spend the transaction!"
+        );
+
+        assert!(UtxoStore::<Test>::contains_key(tx.outpoint(0)));
+        assert_ok!(crate::check_contract_balance::<Test>(&contract));
+
+        // only the contract itself does.
+        assert_ok!(crate::spend_contract_utxos::<Test>(
+            &contract,
+            &vec![tx.outpoint(0)],
+            vec![TransactionOutput::new_pubkey(30, caller)]
+        ));
+        assert!(!UtxoStore::<Test>::contains_key(tx.outpoint(0)));
+    })
+}
+
+#[test]
+fn test_utxo_api_accessors() {
+    execute_with_alice(|alice_pub_key| {
+        let (utxo0, input0) = tx_input_gen_no_signature();
+        let issuance = OutputData::TokenIssuanceV1 {
+            token_ticker: "BensT".as_bytes().to_vec(),
+            amount_to_issue: 1_000_000_000,
+            number_of_decimals: 2,
+            metadata_uri: "mintlayer.org".as_bytes().to_vec(),
+        };
+        let tx = Transaction {
+            inputs: vec![input0],
+            outputs: vec![TransactionOutput {
+                value: ALICE_GENESIS_BALANCE,
+                destination: Destination::Pubkey(alice_pub_key),
+                data: Some(issuance.clone()),
+            }],
+            time_lock: Default::default(),
+        }
+        .sign_unchecked(&[utxo0], 0, &alice_pub_key);
+        assert_ok!(Utxo::spend(Origin::signed(H256::zero()), tx.clone()));
+
+        // only unspent outputs can be read
+        assert_eq!(Utxo::utxo(&tx.outpoint(0)), Some(tx.outputs[0].encode()));
+        assert_eq!(Utxo::utxo(&tx.inputs[0].outpoint), None);
+
+        let token_id = H160::from_slice(&TokenId::new(&tx.inputs[0]).encode());
+        assert_eq!(Utxo::token_issuance(&token_id), Some(issuance.encode()));
+        assert_eq!(Utxo::token_issuance(&H160::zero()), None);
+
+        Timestamp::set_timestamp(1_000_000_000 * 1000);
+        assert_eq!(Utxo::timestamp(), 1_000_000_000);
+    })
+}
+
+#[test]
+fn test_contract_token_spend_with_change() {
+    execute_with_alice(|alice_pub_key| {
+        let caller = H256::from(alice_pub_key);
+        let contract = H256::repeat_byte(5);
+        let (utxo0, input0) = tx_input_gen_no_signature();
+        let tx = Transaction {
+            inputs: vec![input0],
+            outputs: vec![TransactionOutput::new_p2pk_with_data(
+                ALICE_GENESIS_BALANCE - 1,
+                caller,
+                OutputData::TokenIssuanceV1 {
+                    token_ticker: "BensT".as_bytes().to_vec(),
+                    amount_to_issue: 1_000,
+                    number_of_decimals: 2,
+                    metadata_uri: "mintlayer.org".as_bytes().to_vec(),
+                },
+            )],
+            time_lock: Default::default(),
+        }
+        .sign_unchecked(&[utxo0], 0, &alice_pub_key);
+        let token_id = TokenId::new(&tx.inputs[0]);
+        assert_ok!(Utxo::spend(Origin::signed(caller), tx.clone()));
+
+        // all the tokens are paid to the contract.
+        let tx = Transaction {
+            inputs: vec![TransactionInput::new_empty(tx.outpoint(0))],
+            outputs: vec![
+                TransactionOutput::new_call_pp_with_data(
+                    30,
+                    contract,
+                    OutputData::TokenTransferV1 {
+                        token_id: token_id.clone(),
+                        amount: 1_000,
+                    },
+                ),
+                TransactionOutput::new_pubkey(ALICE_GENESIS_BALANCE - 40, caller),
+            ],
+            time_lock: Default::default(),
+        }
+        .sign_unchecked(&[tx.outputs[0].clone()], 0, &alice_pub_key);
+        assert_ok!(Utxo::spend(Origin::signed(caller), tx.clone()));
+        assert_ok!(crate::check_contract_balance::<Test>(&contract));
+        let token = H160::from(token_id.clone());
+        assert_eq!(
+            MockPool::<Test>::contract_token_balance(&contract, &token),
+            Some((1_000, vec![(tx.outpoint(0), 1_000)]))
+        );
+
+        // the contract pays 300 tokens to alice, and gets the rest of the tokens and mlt back.
+        let payout = TransactionOutput::new_p2pk_with_data(
+            0,
+            caller,
+            OutputData::TokenTransferV1 {
+                token_id: token_id.clone(),
+                amount: 300,
+            },
+        );
+        let (_, new_utxos) = crate::spend_contract_utxos::<Test>(
+            &contract,
+            &vec![tx.outpoint(0)],
+            vec![payout.clone()],
+        )
+        .expect("contract spend failed");
+        let change = TransactionOutput::new_call_pp(30, contract, true, vec![], 0);
+        let token_change = TransactionOutput::new_call_pp_with_data(
+            0,
+            contract,
+            OutputData::TokenTransferV1 {
+                token_id: token_id.clone(),
+                amount: 700,
+            },
+        );
+        assert_eq!(new_utxos.len(), 3);
+        assert_eq!(UtxoStore::<Test>::get(new_utxos[0]), Some(payout));
+        assert_eq!(UtxoStore::<Test>::get(new_utxos[1]), Some(change));
+        assert_eq!(UtxoStore::<Test>::get(new_utxos[2]), Some(token_change));
+        assert_ok!(crate::check_contract_balance::<Test>(&contract));
+        assert_eq!(
+            MockPool::<Test>::contract_token_balance(&contract, &token),
+            Some((700, vec![(new_utxos[2], 700)]))
+        );
+
+        // the contract can't send more tokens than its utxos hold.
+        let too_much = vec![TransactionOutput::new_p2pk_with_data(
+            0,
+            caller,
+            OutputData::TokenTransferV1 {
+                token_id,
+                amount: 701,
+            },
+        )];
+        assert_err!(
+            crate::spend_contract_utxos::<Test>(
+                &contract,
+                &vec![new_utxos[1], new_utxos[2]],
+                too_much
+            ),
+            "Contract doesn't have enough tokens"
+        );
+    })
+}
+
+#[test]
+fn test_contract_sweep_to_beneficiary() {
+    execute_with_alice(|alice_pub_key| {
+        let caller = H256::from(alice_pub_key);
+        let contract = H256::repeat_byte(5);
+        let (utxo0, input0) = tx_input_gen_no_signature();
+        let tx = Transaction {
+            inputs: vec![input0],
+            outputs: vec![TransactionOutput::new_p2pk_with_data(
+                ALICE_GENESIS_BALANCE - 1,
+                caller,
+                OutputData::TokenIssuanceV1 {
+                    token_ticker: "BensT".as_bytes().to_vec(),
+                    amount_to_issue: 1_000,
+                    number_of_decimals: 2,
+                    metadata_uri: "mintlayer.org".as_bytes().to_vec(),
+                },
+            )],
+            time_lock: Default::default(),
+        }
+        .sign_unchecked(&[utxo0], 0, &alice_pub_key);
+        let token_id = TokenId::new(&tx.inputs[0]);
+        assert_ok!(Utxo::spend(Origin::signed(caller), tx.clone()));
+
+        let tx = Transaction {
+            inputs: vec![TransactionInput::new_empty(tx.outpoint(0))],
+            outputs: vec![
+                TransactionOutput::new_call_pp_with_data(
+                    30,
+                    contract,
+                    OutputData::TokenTransferV1 {
+                        token_id: token_id.clone(),
+                        amount: 1_000,
+                    },
+                ),
+                TransactionOutput::new_call_pp(20, contract, true, vec![], 0),
+                TransactionOutput::new_pubkey(ALICE_GENESIS_BALANCE - 60, caller),
+            ],
+            time_lock: Default::default(),
+        }
+        .sign_unchecked(&[tx.outputs[0].clone()], 0, &alice_pub_key);
+        assert_ok!(Utxo::spend(Origin::signed(caller), tx.clone()));
+
+        // all the mlt and tokens of the contract go to the beneficiary, with no change.
+        let beneficiary = H256::repeat_byte(7);
+        let new_utxos = Utxo::send_conscrit_sweep(
+            &contract,
+            &Beneficiary::ScriptHash(beneficiary),
+            &vec![tx.outpoint(0), tx.outpoint(1)],
+        )
+        .expect("contract sweep failed");
+        assert_eq!(new_utxos.len(), 2);
+        assert_eq!(
+            UtxoStore::<Test>::get(new_utxos[0]),
+            Some(TransactionOutput::new_script_hash(50, beneficiary))
+        );
+        assert_eq!(
+            UtxoStore::<Test>::get(new_utxos[1]),
+            Some(TransactionOutput {
+                value: 0,
+                destination: Destination::ScriptHash(beneficiary),
+                data: Some(OutputData::TokenTransferV1 {
+                    token_id: token_id.clone(),
+                    amount: 1_000,
+                }),
+            })
+        );
+        assert_ok!(crate::check_contract_balance::<Test>(&contract));
+        assert_eq!(
+            MockPool::<Test>::contract_balance(&contract),
+            Some((0, vec![]))
+        );
+        assert_eq!(
+            MockPool::<Test>::contract_token_balance(&contract, &H160::from(token_id)),
+            Some((0, vec![]))
+        );
+    })
+}
+
+#[test]
+fn test_dry_run_records_contract_transactions() {
+    execute_with_alice(|alice_pub_key| {
+        let caller = H256::from(alice_pub_key);
+        let contract = H256::repeat_byte(5);
+
+        let (utxo, transactions) = Utxo::dry_run(&contract, 50, true, &vec![], 0, |utxo| {
+            // the simulated utxo is stored as if a transaction had paid it to the contract
+            assert_eq!(
+                UtxoStore::<Test>::get(utxo),
+                Some(TransactionOutput::new_call_pp(
+                    50,
+                    contract,
+                    true,
+                    vec![],
+                    0
+                ))
+            );
+            assert_ok!(MockPool::<Test>::call(
+                &caller,
+                &contract,
+                0,
+                utxo,
+                50,
+                None,
+                true,
+                &vec![]
+            ));
+
+            let new_utxos = Utxo::send_conscrit_p2pk(&contract, &caller, 20, &vec![utxo])
+                .expect("contract spend failed");
+            assert_eq!(new_utxos.len(), 2);
+            utxo
+        });
+
+        assert_eq!(transactions.len(), 1);
+        let tx = Transaction::<AccountId>::decode(&mut &transactions[0][..])
+            .expect("failed to decode the transaction");
+        assert_eq!(tx.inputs, vec![TransactionInput::new_empty(utxo)]);
+        assert_eq!(
+            tx.outputs,
+            vec![
+                TransactionOutput::new_pubkey(20, caller),
+                TransactionOutput::new_call_pp(30, contract, true, vec![], 0),
+            ]
+        );
+
+        // nothing of the dry run is left, the simulated utxo included.
+        assert_eq!(UtxoStore::<Test>::get(utxo), None);
+        assert_eq!(UtxoStore::<Test>::get(tx.outpoint(0)), None);
+        assert_eq!(UtxoStore::<Test>::get(tx.outpoint(1)), None);
+    })
+}
+
+#[test]
+fn test_migrate_outputs_to_v1() {
+    use crate::{migrations, LockedUtxos, ValidatorPreferences};
+    use frame_support::{storage::unhashed, traits::StorageVersion};
+
+    let (mut test_ext, alice_pub_key, karl_pub_key) = alice_test_ext_and_keys();
+    test_ext.execute_with(|| {
+        let alice = H256::from(alice_pub_key);
+        let karl = H256::from(karl_pub_key);
+
+        // outputs stored before the gas limit and the validator preferences were added.
+        let call_hash = H256::repeat_byte(1);
+        let old_call = migrations::v0::TransactionOutput {
+            value: 10,
+            destination: migrations::v0::Destination::CallPP(karl, true, vec![1, 2, 3]),
+            data: None,
+        };
+        unhashed::put_raw(
+            &UtxoStore::<Test>::hashed_key_for(call_hash),
+            &old_call.encode(),
+        );
+        let locked_hash = H256::repeat_byte(2);
+        let old_locked = migrations::v0::TransactionOutput {
+            value: 20,
+            destination: migrations::v0::Destination::LockForStaking {
+                stash_account: alice,
+                controller_account: karl,
+                session_key: vec![4, 5],
+            },
+            data: None,
+        };
+        unhashed::put_raw(
+            &LockedUtxos::<Test>::hashed_key_for(locked_hash),
+            &old_locked.encode(),
+        );
+        StorageVersion::new(0).put::<Utxo>();
+
+        assert_ok!(migrations::pre_migrate_to_v1::<Test>());
+        assert_err!(
+            migrations::post_migrate_to_v1::<Test>(),
+            "pallet-utxo storage was not migrated to version 1"
+        );
+        migrations::migrate_to_v1::<Test>();
+        assert_ok!(migrations::post_migrate_to_v1::<Test>());
+
+        assert_eq!(
+            UtxoStore::<Test>::get(call_hash),
+            Some(TransactionOutput {
+                value: 10,
+                destination: Destination::CallPP(karl, true, vec![1, 2, 3], 0),
+                data: None,
+            })
+        );
+        assert_eq!(
+            LockedUtxos::<Test>::get(locked_hash),
+            Some(TransactionOutput {
+                value: 20,
+                destination: Destination::LockForStaking {
+                    stash_account: alice,
+                    controller_account: karl,
+                    session_key: vec![4, 5],
+                    validator_prefs: ValidatorPreferences::default(),
+                },
+                data: None,
+            })
+        );
+        // the genesis output is unchanged.
+        assert_eq!(
+            UtxoStore::<Test>::get(genesis_utxo().1),
+            Some(genesis_utxo().0)
+        );
+        assert_eq!(StorageVersion::get::<Utxo>(), 1);
+    })
+}
+
+#[cfg(feature = "try-runtime")]
+#[test]
+fn test_try_runtime_upgrade_to_v1() {
+    use crate::{migrations, LockedUtxos};
+    use frame_support::{
+        storage::unhashed,
+        traits::{OnRuntimeUpgrade, StorageVersion},
+    };
+
+    let (mut test_ext, alice_pub_key, karl_pub_key) = alice_test_ext_and_keys();
+    test_ext.execute_with(|| {
+        let alice = H256::from(alice_pub_key);
+        let karl = H256::from(karl_pub_key);
+
+        // a stake locked before the validator preferences were added.
+        let old_locked = migrations::v0::TransactionOutput {
+            value: 20,
+            destination: migrations::v0::Destination::LockForStaking {
+                stash_account: alice,
+                controller_account: karl,
+                session_key: vec![4, 5],
+            },
+            data: None,
+        };
+        unhashed::put_raw(
+            &LockedUtxos::<Test>::hashed_key_for(H256::repeat_byte(2)),
+            &old_locked.encode(),
+        );
+        StorageVersion::new(0).put::<Utxo>();
+
+        assert_ok!(<Utxo as OnRuntimeUpgrade>::pre_upgrade());
+        <Utxo as OnRuntimeUpgrade>::on_runtime_upgrade();
+        assert_ok!(<Utxo as OnRuntimeUpgrade>::post_upgrade());
+        assert_eq!(StorageVersion::get::<Utxo>(), 1);
+    })
+}
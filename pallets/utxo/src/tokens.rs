@@ -66,6 +66,18 @@ impl TokenId {
     }
 }
 
+impl From<H160> for TokenId {
+    fn from(inner: H160) -> TokenId {
+        TokenId { inner }
+    }
+}
+
+impl From<TokenId> for H160 {
+    fn from(token_id: TokenId) -> H160 {
+        token_id.inner
+    }
+}
+
 #[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
 #[derive(Clone, Encode, Decode, Eq, PartialEq, PartialOrd, Ord, RuntimeDebug)]
 pub enum OutputData {
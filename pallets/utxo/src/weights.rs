@@ -75,4 +75,112 @@ impl<T: frame_system::Config> crate::WeightInfo for WeightInfo<T> {
             .saturating_add(T::DbWeight::get().reads(3 as Weight))
             .saturating_add(T::DbWeight::get().writes(3 as Weight))
     }
+
+    //TODO this needs a benchmark
+    // Storage: Utxo StakingCount (r:1 w:0)
+    // Storage: Utxo LockedUtxos (r:1 w:0)
+    // Storage: Utxo PartiallyUnlockedStake (r:0 w:1)
+    // Storage: Staking Bonded (r:1 w:0)
+    // Storage: Staking Ledger (r:1 w:1)
+    // Storage: Staking CurrentEra (r:1 w:0)
+    // Storage: Staking Nominators (r:1 w:0)
+    // Storage: Staking MinNominatorBond (r:1 w:0)
+    // Storage: Balances Locks (r:1 w:1)
+    // Storage: System Account (r:1 w:1)
+    fn partial_unlock_request_for_withdrawal() -> Weight {
+        (60_000_000 as Weight)
+            .saturating_add(T::DbWeight::get().reads(9 as Weight))
+            .saturating_add(T::DbWeight::get().writes(4 as Weight))
+    }
+
+    //TODO this needs a benchmark
+    fn set_emission_schedule(s: u32) -> Weight {
+        (10_000_000 as Weight)
+            .saturating_add((100_000 as Weight).saturating_mul(s as Weight))
+            .saturating_add(T::DbWeight::get().writes(1 as Weight))
+    }
+
+    //TODO this needs a benchmark
+    // Storage: Staking CurrentEra (r:1 w:0)
+    // Storage: Staking HistoryDepth (r:1 w:0)
+    // Storage: Staking ErasValidatorReward (r:1 w:0)
+    // Storage: Staking Bonded (r:1 w:0)
+    // Storage: Staking Ledger (r:1 w:1)
+    // Storage: Staking ErasStakersClipped (r:1 w:0)
+    // Storage: Staking ErasRewardPoints (r:1 w:0)
+    // Storage: Staking ErasValidatorPrefs (r:1 w:0)
+    // For each of the `n` rewarded stakers:
+    // Storage: Staking Payee (r:1 w:0)
+    // Storage: System Account (r:1 w:1)
+    // Storage: Utxo StakingRewardDestination (r:1 w:0)
+    // Storage: Utxo UtxoStore (r:1 w:1)
+    fn payout_stakers(n: u32) -> Weight {
+        (80_000_000 as Weight)
+            .saturating_add((40_000_000 as Weight).saturating_mul(n as Weight))
+            .saturating_add(T::DbWeight::get().reads(8 as Weight))
+            .saturating_add(T::DbWeight::get().reads((4 as Weight).saturating_mul(n as Weight)))
+            .saturating_add(T::DbWeight::get().writes(1 as Weight))
+            .saturating_add(T::DbWeight::get().writes((2 as Weight).saturating_mul(n as Weight)))
+    }
+
+    //TODO this needs a benchmark
+    fn set_staking_reward_destination(_s: u32) -> Weight {
+        (10_000_000 as Weight)
+            .saturating_add(T::DbWeight::get().reads(1 as Weight))
+            .saturating_add(T::DbWeight::get().writes(1 as Weight))
+    }
+
+    //TODO this needs a benchmark
+    // Storage: Utxo StakingCount (r:1 w:0)
+    // Storage: Utxo LockedUtxos (r:1 w:1)
+    // Storage: Staking Bonded (r:1 w:0)
+    // Storage: Staking Ledger (r:1 w:0)
+    // Storage: Staking MinValidatorBond (r:1 w:0)
+    // Storage: Staking Nominators (r:1 w:1)
+    // Storage: Staking Validators (r:1 w:1)
+    fn set_validator_preferences() -> Weight {
+        (50_000_000 as Weight)
+            .saturating_add(T::DbWeight::get().reads(7 as Weight))
+            .saturating_add(T::DbWeight::get().writes(3 as Weight))
+    }
+
+    //TODO this needs a benchmark
+    // Storage: Utxo StakingCount (r:1 w:0)
+    // Storage: Utxo LockedUtxos (r:1 w:1)
+    // Storage: Staking Bonded (r:1 w:0)
+    // Storage: Session NextKeys (r:1 w:1)
+    // Storage: Session KeyOwner (r:2 w:4)
+    // The `k` bytes of the session key are decoded, and stored in the locked utxo.
+    fn rotate_session_keys(k: u32) -> Weight {
+        (50_000_000 as Weight)
+            .saturating_add((2_000 as Weight).saturating_mul(k as Weight))
+            .saturating_add(T::DbWeight::get().reads(6 as Weight))
+            .saturating_add(T::DbWeight::get().writes(6 as Weight))
+    }
+
+    //TODO this needs a benchmark
+    // Storage: Utxo StakingCount (r:2 w:0)
+    // Storage: Utxo LockedUtxos (r:1 w:1)
+    // Storage: Staking Bonded (r:1 w:1)
+    // Storage: Staking Ledger (r:2 w:2)
+    fn change_controller() -> Weight {
+        (40_000_000 as Weight)
+            .saturating_add(T::DbWeight::get().reads(6 as Weight))
+            .saturating_add(T::DbWeight::get().writes(4 as Weight))
+    }
+
+    //TODO this needs a benchmark
+    // Storage: Utxo StakingCount (r:1 w:0)
+    // Storage: Utxo PartiallyUnlockedStake (r:1 w:1)
+    // Storage: Utxo LockedUtxos (r:1 w:0)
+    // Storage: Staking Bonded (r:1 w:0)
+    // Storage: Staking Ledger (r:1 w:1)
+    // Storage: Staking Validators or Staking Nominators (r:1 w:1)
+    // Storage: Balances Locks (r:1 w:1)
+    // Storage: System Account (r:1 w:1)
+    fn rebond() -> Weight {
+        (60_000_000 as Weight)
+            .saturating_add(T::DbWeight::get().reads(8 as Weight))
+            .saturating_add(T::DbWeight::get().writes(5 as Weight))
+    }
 }
@@ -3,6 +3,7 @@
 #![recursion_limit = "256"]
 
 mod staking;
+mod uncles;
 
 // Make the WASM binary available.
 #[cfg(feature = "std")]
@@ -55,6 +56,7 @@ pub use pallet_utxo;
 use pallet_utxo::MLT_UNIT;
 use sp_runtime::transaction_validity::{InvalidTransaction, TransactionValidityError};
 pub use staking::*;
+use uncles::AuraSealVerify;
 
 /// An index to a block.
 pub type BlockNumber = u32;
@@ -111,12 +113,12 @@ pub const VERSION: RuntimeVersion = RuntimeVersion {
     // The version of the runtime specification. A full node will not attempt to use its native
     //   runtime in substitute for the on-chain Wasm runtime unless all of `spec_name`,
     //   `spec_version`, and `authoring_version` are the same between Wasm and native.
-    // This value is set to 100 to notify Polkadot-JS App (https://polkadot.js.org/apps) to use
-    //   the compatible custom types.
-    spec_version: 100,
+    // This value started at 100 to notify Polkadot-JS App (https://polkadot.js.org/apps) to use
+    //   the compatible custom types. It is bumped on every change of the runtime logic.
+    spec_version: 101,
     impl_version: 1,
     apis: RUNTIME_API_VERSIONS,
-    transaction_version: 1,
+    transaction_version: 2,
 };
 
 /// This determines the average expected block time that we are targeting.
@@ -147,6 +149,7 @@ pub const DOLLARS: Balance = 100 * CENTS;
 /// The initial supply of mlt coins
 pub const TEST_NET_MLT_ORIG_SUPPLY: Balance = 400_000_000_000 * MLT_UNIT;
 pub const MINIMUM_STAKE: Balance = 40_000 * MLT_UNIT;
+pub const MINIMUM_NOMINATOR_STAKE: Balance = 1_000 * MLT_UNIT;
 
 const fn deposit(items: u32, bytes: u32) -> Balance {
     items as Balance * 15 * CENTS + (bytes as Balance) * 6 * CENTS
@@ -255,6 +258,7 @@ impl pallet_grandpa::Config for Runtime {
         GrandpaId,
     )>>::IdentificationTuple;
 
+    // equivocations are not reported to `pallet-offences` yet, so nothing is slashed for them.
     type HandleEquivocation = ();
 
     type WeightInfo = ();
@@ -314,11 +318,26 @@ impl pallet_template::Config for Runtime {
 
 parameter_types! {
     pub const MinimumStake: u128 = MINIMUM_STAKE;
+    pub const MinimumNominatorStake: u128 = MINIMUM_NOMINATOR_STAKE;
     pub const StakeWithdrawalFee: u128 =  1 * MLT_UNIT;
     pub const RewardReductionPeriod: BlockNumber = 1 * YEARS; // reward reduced every year
     pub const RewardReductionFraction: Percent = Percent::from_percent(25); // reward reduced at 25%
     pub const InitialReward: u128 = 100 * MLT_UNIT;
     pub const DefaultMinimumReward: u128 = 1;
+    // same as Bitcoin's coinbase maturity
+    pub const RewardMaturity: BlockNumber = 100;
+    //TODO: replace with the script hash of the actual treasury
+    pub BlockRewardDistribution: pallet_utxo::RewardDistribution = pallet_utxo::RewardDistribution {
+        author: Percent::from_percent(90),
+        stakers: Percent::from_percent(10),
+        treasury: H256::zero(),
+    };
+    pub const UncleRewardFraction: Percent = Percent::from_percent(50);
+    pub const MissingAuthor: pallet_utxo::MissingAuthorPolicy =
+        pallet_utxo::MissingAuthorPolicy::Treasury;
+    pub const StakeSlashPolicy: pallet_utxo::SlashPolicy = pallet_utxo::SlashPolicy::Burn;
+    // a smart contract using the weight of 1 second costs 10 MLT
+    pub const GasPrice: u128 = 1;
 }
 
 impl pallet_utxo::Config for Runtime {
@@ -326,9 +345,15 @@ impl pallet_utxo::Config for Runtime {
     type Call = Call;
     type WeightInfo = pallet_utxo::weights::WeightInfo<Runtime>;
     type ProgrammablePool = pallet_pp::Pallet<Runtime>;
+    type GasPrice = GasPrice;
 
     type RewardReductionFraction = RewardReductionFraction;
     type RewardReductionPeriod = RewardReductionPeriod;
+    type RewardMaturity = RewardMaturity;
+    type RewardDistribution = BlockRewardDistribution;
+    type UncleRewardFraction = UncleRewardFraction;
+    type MaxUncleAge = UncleGenerations;
+    type MissingAuthorPolicy = MissingAuthor;
 
     fn authorities() -> Vec<H256> {
         Aura::authorities()
@@ -342,7 +367,10 @@ impl pallet_utxo::Config for Runtime {
 
     type StakingHelper = StakeOps<Runtime>;
     type MinimumStake = MinimumStake;
+    type MinimumNominatorStake = MinimumNominatorStake;
     type StakeWithdrawalFee = StakeWithdrawalFee;
+    type MaxNominatorRewardedPerValidator = MaxNominatorRewardedPerValidator;
+    type SlashPolicy = StakeSlashPolicy;
     type InitialReward = InitialReward;
     type DefaultMinimumReward = DefaultMinimumReward;
 }
@@ -414,6 +442,15 @@ impl pallet_session_historical::Config for Runtime {
     type FullIdentificationOf = pallet_staking::ExposureOf<Runtime>;
 }
 
+// Offences reported here are slashed by `pallet-staking`, and the slash is also
+// applied to the locked utxos of the offenders.
+// NOTE: no offence is reported yet; there is no equivocation handler nor `pallet-im-online`.
+impl pallet_offences::Config for Runtime {
+    type Event = Event;
+    type IdentificationTuple = pallet_session_historical::IdentificationTuple<Self>;
+    type OnOffenceHandler = SlashLockedUtxos<Runtime>;
+}
+
 impl pallet_session::Config for Runtime {
     type Event = Event;
     type ValidatorId = AccountId;
@@ -445,7 +482,7 @@ parameter_types! {
     pub const BondingDuration: pallet_staking::EraIndex = 2;
 
     pub const SlashDeferDuration: pallet_staking::EraIndex = 0;
-    pub const MaxNominatorRewardedPerValidator: u32 = 0;
+    pub const MaxNominatorRewardedPerValidator: u32 = 64;
     pub OffchainRepeat: BlockNumber = 5;
 }
 
@@ -457,7 +494,7 @@ impl pallet_staking::Config for Runtime {
     type CurrencyToVote = U128CurrencyToVote;
     type ElectionProvider = onchain::OnChainSequentialPhragmen<Self>;
     type GenesisElectionProvider = Self::ElectionProvider;
-    const MAX_NOMINATIONS: u32 = 0;
+    const MAX_NOMINATIONS: u32 = 16;
     type RewardRemainder = (); // Treasury, or something similar
     type Event = Event;
     type Slash = (); // Treasury, or something similar
@@ -467,6 +504,7 @@ impl pallet_staking::Config for Runtime {
     type SlashDeferDuration = SlashDeferDuration;
     type SlashCancelOrigin = frame_system::EnsureRoot<Self::AccountId>;
     type SessionInterface = Self;
+    // no era reward: the stakers get their share of every block reward as utxos instead.
     type EraPayout = (); // pallet_staking::ConvertCurve<RewardCurve>;
     type NextNewSession = Session;
     type MaxNominatorRewardedPerValidator = MaxNominatorRewardedPerValidator;
@@ -482,7 +520,7 @@ parameter_types! {
 impl pallet_authorship::Config for Runtime {
     type FindAuthor = pallet_session::FindAccountFromAuthorIndex<Self, Aura>;
     type UncleGenerations = UncleGenerations;
-    type FilterUncle = ();
+    type FilterUncle = pallet_authorship::OnePerAuthorPerHeight<AuraSealVerify, BlockNumber>;
     type EventHandler = pallet_utxo::Pallet<Runtime>;
 }
 
@@ -518,6 +556,7 @@ construct_runtime!(
         Session: pallet_session::{Pallet, Call, Config<T>, Storage, Event},
         Aura: pallet_aura::{Pallet, Config<T>},
         Historical: pallet_session_historical::{Pallet},
+        Offences: pallet_offences::{Pallet, Storage, Event},
     }
 );
 
@@ -702,6 +741,45 @@ impl_runtime_apis! {
         fn send() -> u32 {
             Utxo::send()
         }
+
+        fn utxo_maturity(outpoint: H256) -> Option<BlockNumber> {
+            Utxo::utxo_maturity(outpoint)
+        }
+
+        fn mlt_supply() -> pallet_utxo::supply::Supply {
+            Utxo::mlt_supply()
+        }
+
+        fn token_supply(
+            token_id: pallet_utxo::tokens::TokenId,
+        ) -> Option<pallet_utxo::supply::Supply> {
+            Utxo::token_supply(token_id)
+        }
+
+        fn block_reward(block_number: BlockNumber) -> pallet_utxo::tokens::Value {
+            Utxo::block_reward(block_number)
+        }
+    }
+
+    impl pallet_pp_rpc_runtime_api::PpApi<Block, AccountId> for Runtime {
+        fn contract_balance(contract: AccountId) -> Option<pallet_pp::ContractBalance> {
+            Pp::contract_balances(contract)
+        }
+
+        fn contracts() -> Vec<(AccountId, H256)> {
+            Pp::contracts()
+        }
+
+        fn dry_run_call(
+            caller: AccountId,
+            dest: AccountId,
+            value: u128,
+            fund_contract: bool,
+            input_data: Vec<u8>,
+            gas_limit: u64,
+        ) -> Result<pallet_pp::DryRunResult, sp_runtime::DispatchError> {
+            Pp::dry_run_call(caller, dest, value, fund_contract, input_data, gas_limit)
+        }
     }
 
     impl pallet_contracts_rpc_runtime_api::ContractsApi<
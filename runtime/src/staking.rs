@@ -15,16 +15,20 @@
 //
 // Author(s): C. Yap
 
-use crate::Perbill;
-
 use codec::Decode;
 use frame_support::dispatch::{DispatchResult, DispatchResultWithPostInfo, Vec};
 use frame_support::fail;
+use frame_support::weights::Weight;
 use frame_system::{Config as SysConfig, RawOrigin};
-use pallet_staking::{BalanceOf, Pallet as StakingPallet};
-use pallet_utxo::staking::StakingHelper;
-use sp_core::sp_std::vec;
-use sp_runtime::traits::StaticLookup;
+use pallet_staking::{BalanceOf, Pallet as StakingPallet, RewardDestination};
+use pallet_session::historical::IdentificationTuple;
+use pallet_utxo::staking::{StakingHelper, ValidatorPreferences};
+use sp_core::sp_std::{collections::btree_set::BTreeSet, vec};
+use sp_runtime::Perbill;
+use sp_staking::offence::{OffenceDetails, OnOffenceHandler};
+use sp_staking::SessionIndex;
+use sp_runtime::traits::{Saturating, StaticLookup, Zero};
+use sp_runtime::{DispatchError, SaturatedConversion};
 
 type StakeAccountId<T> = <T as SysConfig>::AccountId;
 type LookupSourceOf<T> = <<T as SysConfig>::Lookup as StaticLookup>::Source;
@@ -54,7 +58,8 @@ where
         value: pallet_utxo::tokens::Value,
     ) -> DispatchResult {
         let controller_lookup: LookupSourceOf<T> = T::Lookup::unlookup(controller_account.clone());
-        let reward_destination = pallet_staking::RewardDestination::Staked;
+        // the era rewards are paid out as utxos, not to an account. see `payout_stakers`.
+        let reward_destination = RewardDestination::None;
 
         // bond the funds
         StakingPallet::<T>::bond(
@@ -75,13 +80,20 @@ where
         )
     }
 
+    fn unbond_value(
+        controller_account: StakeAccountId<T>,
+        value: pallet_utxo::tokens::Value,
+    ) -> DispatchResult {
+        StakingPallet::<T>::unbond(RawOrigin::Signed(controller_account).into(), value.into())
+    }
+
     fn set_session_keys(
         controller_account: StakeAccountId<T>,
         session_key: &Vec<u8>,
     ) -> DispatchResult {
         // session keys
         let sesh_key = <T as pallet_session::Config>::Keys::decode(&mut &session_key[..])
-            .expect("SessionKeys decoded successfully");
+            .map_err(|_| DispatchError::Other("cannot decode the session key"))?;
         pallet_session::Pallet::<T>::set_keys(
             RawOrigin::Signed(controller_account).into(),
             sesh_key,
@@ -89,10 +101,23 @@ where
         )
     }
 
-    fn apply_for_validator_role(controller_account: StakeAccountId<T>) -> DispatchResult {
+    fn apply_for_nominator_role(
+        controller_account: StakeAccountId<T>,
+        targets: &Vec<StakeAccountId<T>>,
+    ) -> DispatchResult {
+        let targets: Vec<LookupSourceOf<T>> =
+            targets.iter().cloned().map(T::Lookup::unlookup).collect();
+
+        StakingPallet::<T>::nominate(RawOrigin::Signed(controller_account).into(), targets)
+    }
+
+    fn apply_for_validator_role(
+        controller_account: StakeAccountId<T>,
+        validator_prefs: &ValidatorPreferences,
+    ) -> DispatchResult {
         let validator_prefs = pallet_staking::ValidatorPrefs {
-            commission: Perbill::from_percent(0),
-            ..Default::default()
+            commission: validator_prefs.commission,
+            blocked: validator_prefs.blocked,
         };
 
         // applying for the role of "validator".
@@ -125,23 +150,33 @@ where
     }
 
     fn are_funds_locked(controller_account: &StakeAccountId<T>) -> bool {
-        // Information of unlocked funds are found in the `pallet-staking` ledger.
+        // Information of locked funds are found in the `pallet-staking` ledger.
         // The ledger is stored as a map, with the controller_account as the key.
+        // After a partial unlock, what is left of the stake is still active.
         match <StakingPallet<T>>::ledger(controller_account.clone()) {
             None => {
                 log::error!("Controller account {:?} not found", controller_account);
+                false
+            }
+            Some(stake_ledger) => !stake_ledger.active.is_zero(),
+        }
+    }
+
+    fn has_unlocking_funds(controller_account: &StakeAccountId<T>) -> bool {
+        match <StakingPallet<T>>::ledger(controller_account.clone()) {
+            None => {
+                log::error!("Controller account {:?} not found", controller_account);
+                false
             }
             Some(stake_ledger) => {
-                if stake_ledger.unlocking.is_empty() {
-                    return true;
-                } else if stake_ledger.unlocking.len() > 1 {
+                if stake_ledger.unlocking.len() > 1 {
                     log::error!(
                         "Pallet-staking ledger's unlocking field should only contain ONE element."
                     );
                 }
+                !stake_ledger.unlocking.is_empty()
             }
         }
-        false
     }
 
     fn check_accounts_matched(
@@ -166,10 +201,25 @@ where
         controller_account: &StakeAccountId<T>,
         session_key: &Vec<u8>,
         value: u128,
+        validator_prefs: &ValidatorPreferences,
     ) -> DispatchResultWithPostInfo {
         Self::bond(controller_account.clone(), stash_account.clone(), value)?;
         Self::set_session_keys(controller_account.clone(), session_key)?;
-        Self::apply_for_validator_role(controller_account.clone())?;
+        Self::apply_for_validator_role(controller_account.clone(), validator_prefs)?;
+
+        Ok(().into())
+    }
+
+    fn set_validator_preferences(
+        stash_account: &StakeAccountId<T>,
+        validator_prefs: &ValidatorPreferences,
+    ) -> DispatchResultWithPostInfo {
+        // get the controller account, given the stash_account.
+        let controller_account = <StakingPallet<T>>::bonded(stash_account.clone())
+            .ok_or(pallet_utxo::Error::<T>::StashAccountNotFound)?;
+
+        // calling `validate` again replaces the preferences.
+        Self::apply_for_validator_role(controller_account, validator_prefs)?;
 
         Ok(().into())
     }
@@ -186,6 +236,81 @@ where
         Ok(().into())
     }
 
+    fn rotate_session_keys(
+        stash_account: &StakeAccountId<T>,
+        session_key: &Vec<u8>,
+    ) -> DispatchResultWithPostInfo {
+        // get the controller account, given the stash_account.
+        let controller_account = <StakingPallet<T>>::bonded(stash_account.clone())
+            .ok_or(pallet_utxo::Error::<T>::StashAccountNotFound)?;
+
+        // setting the keys again replaces the old ones.
+        Self::set_session_keys(controller_account, session_key)?;
+
+        Ok(().into())
+    }
+
+    fn change_controller(
+        stash_account: &StakeAccountId<T>,
+        controller_account: &StakeAccountId<T>,
+    ) -> DispatchResultWithPostInfo {
+        let controller_lookup: LookupSourceOf<T> = T::Lookup::unlookup(controller_account.clone());
+
+        StakingPallet::<T>::set_controller(
+            RawOrigin::Signed(stash_account.clone()).into(),
+            controller_lookup,
+        )?;
+
+        Ok(().into())
+    }
+
+    fn are_valid_nomination_targets(targets: &Vec<StakeAccountId<T>>) -> bool {
+        targets.len() <= T::MAX_NOMINATIONS as usize
+            && targets
+                .iter()
+                .all(|target| <pallet_staking::Validators<T>>::contains_key(target))
+    }
+
+    fn lock_for_nominating(
+        stash_account: &StakeAccountId<T>,
+        targets: &Vec<StakeAccountId<T>>,
+        value: u128,
+    ) -> DispatchResultWithPostInfo {
+        // the stash account is its own controller.
+        Self::bond(stash_account.clone(), stash_account.clone(), value)?;
+        Self::apply_for_nominator_role(stash_account.clone(), targets)?;
+
+        Ok(().into())
+    }
+
+    fn nominate(
+        stash_account: &StakeAccountId<T>,
+        targets: &Vec<StakeAccountId<T>>,
+    ) -> DispatchResultWithPostInfo {
+        // get the controller account, given the stash_account.
+        let controller_account = <StakingPallet<T>>::bonded(stash_account.clone())
+            .ok_or(pallet_utxo::Error::<T>::StashAccountNotFound)?;
+
+        Self::apply_for_nominator_role(controller_account, targets)?;
+
+        Ok(().into())
+    }
+
+    fn rebond(stash_account: &StakeAccountId<T>) -> DispatchResultWithPostInfo {
+        // get the controller account, given the stash_account.
+        let controller_account = <StakingPallet<T>>::bonded(stash_account.clone())
+            .ok_or(pallet_utxo::Error::<T>::StashAccountNotFound)?;
+
+        let stake_ledger = <StakingPallet<T>>::ledger(controller_account.clone())
+            .ok_or(pallet_utxo::Error::<T>::ControllerAccountNotFound)?;
+        let unlocking = stake_ledger
+            .unlocking
+            .iter()
+            .fold(BalanceOf::<T>::zero(), |total, chunk| total.saturating_add(chunk.value));
+
+        StakingPallet::<T>::rebond(RawOrigin::Signed(controller_account).into(), unlocking)
+    }
+
     fn unlock_request_for_withdrawal(
         stash_account: &StakeAccountId<T>,
     ) -> DispatchResultWithPostInfo {
@@ -193,7 +318,7 @@ where
         let controller_account = <StakingPallet<T>>::bonded(stash_account.clone())
             .ok_or(pallet_utxo::Error::<T>::StashAccountNotFound)?;
 
-        // stop validating / block producing
+        // stop validating / block producing, or nominating
         StakingPallet::<T>::chill(RawOrigin::Signed(controller_account.clone()).into())?;
 
         // unbond
@@ -202,6 +327,20 @@ where
         Ok(().into())
     }
 
+    fn partial_unlock_request_for_withdrawal(
+        stash_account: &StakeAccountId<T>,
+        value: u128,
+    ) -> DispatchResultWithPostInfo {
+        // get the controller account, given the stash_account.
+        let controller_account = <StakingPallet<T>>::bonded(stash_account.clone())
+            .ok_or(pallet_utxo::Error::<T>::StashAccountNotFound)?;
+
+        // unbond only the value; the validator keeps on validating.
+        Self::unbond_value(controller_account, value)?;
+
+        Ok(().into())
+    }
+
     fn withdraw(stash_account: &StakeAccountId<T>) -> DispatchResultWithPostInfo {
         // get the controller account, given the stash_account.
         let controller_account = <StakingPallet<T>>::bonded(stash_account.clone())
@@ -220,4 +359,170 @@ where
 
         Ok(res)
     }
+
+    fn withdraw_partial(stash_account: &StakeAccountId<T>) -> DispatchResultWithPostInfo {
+        // get the controller account, given the stash_account.
+        let controller_account = <StakingPallet<T>>::bonded(stash_account.clone())
+            .ok_or(pallet_utxo::Error::<T>::StashAccountNotFound)?;
+
+        let res = StakingPallet::<T>::withdraw_unbonded(
+            RawOrigin::Signed(controller_account.clone()).into(),
+            0,
+        )?;
+
+        // the rest of the stake should still be there, with nothing left to unlock.
+        match <StakingPallet<T>>::ledger(controller_account) {
+            Some(stake_ledger) if stake_ledger.unlocking.is_empty() => Ok(res),
+            Some(_) => {
+                log::error!("no withdrawal was done.");
+                fail!("not yet time to withdraw")
+            }
+            None => {
+                log::error!("the whole stake was withdrawn.");
+                fail!(pallet_utxo::Error::<T>::InvalidOperation)
+            }
+        }
+    }
+
+    fn remove_stake(stash_account: &StakeAccountId<T>) -> DispatchResultWithPostInfo {
+        // get the controller account, given the stash_account.
+        let controller_account = <StakingPallet<T>>::bonded(stash_account.clone())
+            .ok_or(pallet_utxo::Error::<T>::StashAccountNotFound)?;
+
+        // stop validating / block producing, or nominating
+        StakingPallet::<T>::chill(RawOrigin::Signed(controller_account).into())?;
+
+        // unbond and reap the stash at once; its slashing spans don't matter anymore.
+        StakingPallet::<T>::force_unstake(RawOrigin::Root.into(), stash_account.clone(), u32::MAX)
+    }
+
+    fn payout_stakers(
+        validator_stash: &StakeAccountId<T>,
+        era: u32,
+    ) -> Result<Vec<(StakeAccountId<T>, pallet_utxo::tokens::Value)>, DispatchError> {
+        // the validator and its rewarded nominators.
+        let exposure = <StakingPallet<T>>::eras_stakers_clipped(era, validator_stash);
+
+        // nothing may be paid to an account: the genesis stakers were bonded
+        // with `RewardDestination::Staked`, so they are moved to `None` first.
+        let stashes = vec![validator_stash.clone()]
+            .into_iter()
+            .chain(exposure.others.iter().map(|individual| individual.who.clone()));
+        for stash in stashes {
+            if <StakingPallet<T>>::payee(&stash) == RewardDestination::None {
+                continue;
+            }
+            if let Some(controller_account) = <StakingPallet<T>>::bonded(&stash) {
+                StakingPallet::<T>::set_payee(
+                    RawOrigin::Signed(controller_account).into(),
+                    RewardDestination::None,
+                )?;
+            }
+        }
+
+        // only marks the era as claimed, and checks that it can be.
+        StakingPallet::<T>::payout_stakers(
+            RawOrigin::Signed(validator_stash.clone()).into(),
+            validator_stash.clone(),
+            era,
+        )
+        .map_err(|e| e.error)?;
+
+        // the shares of the era reward, as `pallet-staking` computes them.
+        let era_payout = <StakingPallet<T>>::eras_validator_reward(era)
+            .ok_or(DispatchError::Other("InvalidEraToReward"))?;
+        let reward_points = <StakingPallet<T>>::eras_reward_points(era);
+        let validator_points = reward_points
+            .individual
+            .get(validator_stash)
+            .copied()
+            .unwrap_or_else(Zero::zero);
+        if validator_points.is_zero() || exposure.total.is_zero() {
+            return Ok(Vec::new());
+        }
+
+        let validator_total_payout =
+            Perbill::from_rational(validator_points, reward_points.total) * era_payout;
+        let commission = <StakingPallet<T>>::eras_validator_prefs(era, validator_stash).commission;
+        let commission_payout = commission * validator_total_payout;
+        let leftover_payout = validator_total_payout.saturating_sub(commission_payout);
+
+        let to_value = |payout: BalanceOf<T>| payout.saturated_into::<pallet_utxo::tokens::Value>();
+        let validator_payout = Perbill::from_rational(exposure.own, exposure.total)
+            * leftover_payout
+            + commission_payout;
+        let mut payouts = vec![(validator_stash.clone(), to_value(validator_payout))];
+        for nominator in exposure.others.iter() {
+            let nominator_payout =
+                Perbill::from_rational(nominator.value, exposure.total) * leftover_payout;
+            payouts.push((nominator.who.clone(), to_value(nominator_payout)));
+        }
+
+        Ok(payouts)
+    }
+}
+
+/// Passes the offences to `pallet-staking`, then reduces the locked utxos by what was slashed.
+/// Only works with a `SlashDeferDuration` of 0, so that the slash happens right away.
+pub struct SlashLockedUtxos<T>(sp_core::sp_std::marker::PhantomData<T>);
+
+impl<T> SlashLockedUtxos<T>
+where
+    T: pallet_staking::Config + pallet_utxo::Config,
+{
+    /// the total bonded in `pallet-staking`, including the value being unlocked.
+    fn total_bonded(stash_account: &StakeAccountId<T>) -> BalanceOf<T> {
+        <StakingPallet<T>>::bonded(stash_account)
+            .and_then(|controller_account| <StakingPallet<T>>::ledger(controller_account))
+            .map(|stake_ledger| stake_ledger.total)
+            .unwrap_or_else(Zero::zero)
+    }
+}
+
+impl<T>
+    OnOffenceHandler<StakeAccountId<T>, IdentificationTuple<T>, Weight> for SlashLockedUtxos<T>
+where
+    T: pallet_staking::Config
+        + pallet_utxo::Config
+        + pallet_session::historical::Config<
+            FullIdentification = pallet_staking::Exposure<StakeAccountId<T>, BalanceOf<T>>,
+        >,
+    StakingPallet<T>: OnOffenceHandler<StakeAccountId<T>, IdentificationTuple<T>, Weight>,
+{
+    fn on_offence(
+        offenders: &[OffenceDetails<StakeAccountId<T>, IdentificationTuple<T>>],
+        slash_fraction: &[Perbill],
+        session: SessionIndex,
+    ) -> Weight {
+        // the offending validators, and the nominators exposed to them.
+        // A nominator can be exposed to more than one offender, but is only counted once.
+        let stashes: BTreeSet<StakeAccountId<T>> = offenders
+            .iter()
+            .flat_map(|details| {
+                let (stash_account, exposure) = &details.offender;
+                vec![stash_account.clone()]
+                    .into_iter()
+                    .chain(exposure.others.iter().map(|individual| individual.who.clone()))
+            })
+            .collect();
+        let bonded_before: Vec<BalanceOf<T>> = stashes.iter().map(Self::total_bonded).collect();
+
+        let weight = <StakingPallet<T> as OnOffenceHandler<
+            StakeAccountId<T>,
+            IdentificationTuple<T>,
+            Weight,
+        >>::on_offence(offenders, slash_fraction, session);
+
+        for (stash_account, before) in stashes.into_iter().zip(bonded_before) {
+            let slashed = before.saturating_sub(Self::total_bonded(&stash_account));
+            if !slashed.is_zero() {
+                pallet_utxo::staking::slash_locked_stake::<T>(
+                    &stash_account,
+                    slashed.saturated_into::<pallet_utxo::tokens::Value>(),
+                );
+            }
+        }
+
+        weight
+    }
 }
@@ -0,0 +1,67 @@
+// Copyright (c) 2021 RBB S.r.l
+// opensource@mintlayer.org
+// SPDX-License-Identifier: MIT
+// Licensed under the MIT License;
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// 	http://spdx.org/licenses/MIT
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::{AccountId, Aura, Header, Offset, Period, Session, System};
+
+use frame_support::traits::Get;
+use pallet_authorship::VerifySeal;
+use sp_consensus_aura::{digests::CompatibleDigestItem, sr25519::AuthoritySignature};
+use sp_runtime::traits::Header as HeaderT;
+use sp_runtime::RuntimeAppPublic;
+
+/// Finds the author of an uncle, after checking that the uncle was sealed
+/// by the aura authority of its slot.
+/// Only uncles of the current session are accepted, as the authorities
+/// of an earlier session may not be the same.
+pub struct AuraSealVerify;
+
+impl VerifySeal<Header, AccountId> for AuraSealVerify {
+    fn verify_seal(header: &Header) -> Result<Option<AccountId>, &'static str> {
+        // the session changes at the start of the block, after the authorities of the
+        // previous session authored it, so the new authorities author the blocks that follow.
+        let now = System::block_number();
+        if now >= Offset::get() {
+            let session_start = now - (now - Offset::get()) % Period::get();
+            if *header.number() <= session_start {
+                return Err("uncle is from a previous session");
+            }
+        }
+
+        let mut header = header.clone();
+        let seal = header.digest_mut().pop().ok_or("uncle header is not sealed")?;
+        let signature = CompatibleDigestItem::<AuthoritySignature>::as_aura_seal(&seal)
+            .ok_or("uncle header is not sealed by aura")?;
+
+        let slot = header
+            .digest()
+            .logs()
+            .iter()
+            .find_map(CompatibleDigestItem::<AuthoritySignature>::as_aura_pre_digest)
+            .ok_or("uncle header has no aura slot")?;
+
+        let authorities = Aura::authorities();
+        if authorities.is_empty() {
+            return Err("no aura authorities found");
+        }
+        let author_index = (*slot % authorities.len() as u64) as usize;
+
+        // the seal signs the hash of the header, without the seal itself.
+        if !authorities[author_index].verify(&header.hash(), &signature) {
+            return Err("invalid uncle seal");
+        }
+
+        Ok(Session::validators().get(author_index).cloned())
+    }
+}
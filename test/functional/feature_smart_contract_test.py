@@ -23,6 +23,9 @@ from test_framework.util import (
 
 import os
 
+# the gas price is 1, so the fee paid for each contract is its gas limit
+GAS_LIMIT = 6_000_000_000
+
 
 class ExampleTest(MintlayerTestFramework):
     # Each functional test is a subclass of the MintlayerTestFramework class.
@@ -75,8 +78,9 @@ class ExampleTest(MintlayerTestFramework):
                 utxo.Input(initial_utxo[0]),
             ],
             outputs=[
+                # This output pays the fee for the gas of the contract call below
                 utxo.Output(
-                    value=50,
+                    value=GAS_LIMIT + 50,
                     destination=utxo.DestPubkey(alice.public_key),
                     data=None
                 ),
@@ -85,6 +89,7 @@ class ExampleTest(MintlayerTestFramework):
                     destination=utxo.DestCreatePP(
                         code=os.path.join(os.path.dirname(__file__), "code.wasm"),
                         data=[0xed, 0x4b, 0x9d, 0x1b],  # default() constructor selector
+                        gas_limit=GAS_LIMIT,
                     ),
                     data=None
                 ),
@@ -137,6 +142,7 @@ class ExampleTest(MintlayerTestFramework):
                         dest_account=acc_id,
                         fund=False,
                         input_data=bytes.fromhex(msg_data.to_hex()[2:]),
+                        gas_limit=GAS_LIMIT,
                     ),
                     data=None
                 ),
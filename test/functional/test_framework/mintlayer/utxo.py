@@ -178,6 +178,8 @@ class Destination():
             return DestLockForStaking.load(obj['LockForStaking'])
         if 'LockExtraForStaking' in obj:
             return DestLockExtraForStaking.load(obj['LockExtraForStaking'])
+        if 'LockForNominating' in obj:
+            return DestLockForNominating.load(obj['LockForNominating'])
         return None
 
     def type_string(self):
@@ -205,12 +207,13 @@ class DestPubkey(Destination):
         return self.pubkey
 
 class DestCreatePP(Destination):
-    def __init__(self, code, data):
+    def __init__(self, code, data, gas_limit):
         if type(code) == str:
             with open(code, "rb") as file:
                 code = file.read()
         self.code = code
         self.data = data
+        self.gas_limit = gas_limit
 
     @staticmethod
     def load(obj):
@@ -222,36 +225,41 @@ class DestCreatePP(Destination):
         #
         # convert the bytecode str representation to a byte vector
         code = bytes.fromhex(obj['code'][2:])
-        return DestCreatePP(code, obj['data'])
+        return DestCreatePP(code, obj['data'], obj['gas_limit'])
 
     def json(self):
-        return { 'CreatePP': { 'code': self.code, 'data': self.data } }
+        return { 'CreatePP': { 'code': self.code, 'data': self.data, 'gas_limit': self.gas_limit } }
 
 class DestCallPP(Destination):
-    def __init__(self, dest_account, fund, input_data):
+    def __init__(self, dest_account, fund, input_data, gas_limit):
         self.acct = dest_account
         self.fund = fund
         self.data = input_data
+        self.gas_limit = gas_limit
 
     @staticmethod
     def load(obj):
-        return DestCallPP(obj['dest_account'], obj['fund'], obj['input_data'])
+        return DestCallPP(obj['dest_account'], obj['fund'], obj['input_data'], obj['gas_limit'])
 
     def json(self):
-        return { 'CallPP': { 'dest_account': self.acct, 'fund': self.fund, 'input_data': self.data } }
+        return { 'CallPP': { 'dest_account': self.acct, 'fund': self.fund, 'input_data': self.data, 'gas_limit': self.gas_limit } }
 
 class DestLockForStaking(Destination):
-    def __init__(self, stash_account, controller_account, session_key):
+    def __init__(self, stash_account, controller_account, session_key, commission = 0, blocked = False):
         self.stash = stash_account
         self.controller = controller_account
         self.sesh = session_key
+        self.commission = commission
+        self.blocked = blocked
 
     @staticmethod
     def load(obj):
-        return DestLockForStaking(obj['stash_account'], obj['controller_account'], ['session_key'])
+        prefs = obj['validator_prefs']
+        return DestLockForStaking(obj['stash_account'], obj['controller_account'], obj['session_key'], prefs['commission'], prefs['blocked'])
 
     def json(self):
-        return { 'LockForStaking': { 'stash_account': self.stash, 'controller_account': self.controller, 'session_key': self.sesh } }
+        return { 'LockForStaking': { 'stash_account': self.stash, 'controller_account': self.controller, 'session_key': self.sesh,
+                                     'validator_prefs': { 'commission': self.commission, 'blocked': self.blocked } } }
 
     def get_ss58_address(self):
         return self.stash
@@ -271,6 +279,21 @@ class DestLockExtraForStaking(Destination):
     def get_ss58_address(self):
         return self.stash
 
+class DestLockForNominating(Destination):
+    def __init__(self, stash_account, targets):
+        self.stash = stash_account
+        self.targets = targets
+
+    @staticmethod
+    def load(obj):
+        return DestLockForNominating(obj['stash_account'], obj['targets'])
+
+    def json(self):
+        return { 'LockForNominating': { 'stash_account': self.stash, 'targets': self.targets } }
+
+    def get_ss58_address(self):
+        return self.stash
+
 
 class Output():
     def __init__(self, value, destination, data):
@@ -1,11 +1,13 @@
 #![cfg_attr(not(feature = "std"), no_std)]
 
 use frame_support::{dispatch::Vec, weights::Weight};
-use sp_core::H256;
+use sp_core::{H160, H256};
 
 pub trait ProgrammablePoolApi {
     type AccountId;
 
+    /// Instantiates a smart contract, and returns its account
+    /// along with the gas consumed by the instantiation.
     fn create(
         origin: &Self::AccountId,
         gas: Weight,
@@ -13,15 +15,37 @@ pub trait ProgrammablePoolApi {
         utxo_hash: H256,
         utxo_value: u128,
         data: &Vec<u8>,
-    ) -> Result<(), &'static str>;
+    ) -> Result<(Self::AccountId, Weight), &'static str>;
 
+    /// Calls a smart contract, and returns the gas consumed by the call.
+    /// `utxo_token` is the token id and amount of the tokens the UTXO holds, if any.
     fn call(
         caller: &Self::AccountId,
         dest: &Self::AccountId,
         gas_limit: Weight,
         utxo_hash: H256,
         utxo_value: u128,
+        utxo_token: Option<(H160, u128)>,
         fund_contract: bool,
         input_data: &Vec<u8>,
+    ) -> Result<Weight, &'static str>;
+
+    /// Removes a spent UTXO from the balance of the smart contract it was paid to.
+    fn on_utxo_spent(
+        contract: &Self::AccountId,
+        utxo_hash: H256,
+        utxo_value: u128,
+        utxo_token: Option<(H160, u128)>,
+        fund_contract: bool,
     ) -> Result<(), &'static str>;
+
+    /// Returns the funds of the smart contract, and the UTXOs it owns.
+    fn contract_balance(contract: &Self::AccountId) -> Option<(u128, Vec<(H256, u128)>)>;
+
+    /// Returns the funds of the smart contract in the token with `token_id`,
+    /// and the UTXOs it owns that hold the token, with their token amounts.
+    fn contract_token_balance(
+        contract: &Self::AccountId,
+        token_id: &H160,
+    ) -> Option<(u128, Vec<(H256, u128)>)>;
 }
@@ -16,11 +16,21 @@
 // Author(s): A. Altonen
 #![cfg_attr(not(feature = "std"), no_std)]
 
+use codec::{Decode, Encode};
 use frame_support::{
     dispatch::Vec,
     pallet_prelude::{DispatchError, DispatchResultWithPostInfo},
+    weights::Weight,
+    RuntimeDebug,
 };
-use sp_core::{H256, H512};
+use sp_core::{H160, H256, H512};
+
+/// Where the UTXOs of a terminated smart contract go.
+#[derive(Clone, Encode, Decode, Eq, PartialEq, RuntimeDebug)]
+pub enum Beneficiary {
+    Pubkey(H256),
+    ScriptHash(H256),
+}
 
 pub trait UtxoApi {
     type AccountId;
@@ -39,18 +49,72 @@ pub trait UtxoApi {
 
     fn withdraw_stake(stash_account_caller: &Self::AccountId) -> DispatchResultWithPostInfo;
 
+    /// Returns the outpoints of the new UTXOs.
     fn send_conscrit_p2pk(
         caller: &Self::AccountId,
         destination: &Self::AccountId,
         value: u128,
         outpoints: &Vec<H256>,
-    ) -> Result<(), DispatchError>;
+    ) -> Result<Vec<H256>, DispatchError>;
 
+    /// Returns the gas consumed by the called contract, which the calling
+    /// contract has to pay for, and the outpoints of the new UTXOs.
     fn send_conscrit_c2c(
         caller: &Self::AccountId,
         destination: &Self::AccountId,
         value: u128,
         data: &Vec<u8>,
         outpoints: &Vec<H256>,
-    ) -> Result<(), DispatchError>;
+        gas_limit: Weight,
+    ) -> Result<(Weight, Vec<H256>), DispatchError>;
+
+    /// Sends `amount` of the token with `token_id` to `destination`.
+    /// Returns the outpoints of the new UTXOs.
+    fn send_conscrit_token_p2pk(
+        caller: &Self::AccountId,
+        destination: &Self::AccountId,
+        token_id: &H160,
+        amount: u128,
+        outpoints: &Vec<H256>,
+    ) -> Result<Vec<H256>, DispatchError>;
+
+    /// Sends `amount` of the token with `token_id` to the `destination` contract,
+    /// without calling it. Returns the outpoints of the new UTXOs.
+    fn send_conscrit_token_c2c(
+        caller: &Self::AccountId,
+        destination: &Self::AccountId,
+        token_id: &H160,
+        amount: u128,
+        outpoints: &Vec<H256>,
+    ) -> Result<Vec<H256>, DispatchError>;
+
+    /// Sends everything the given UTXOs hold, in MLT and in tokens, to `beneficiary`.
+    /// Returns the outpoints of the new UTXOs.
+    fn send_conscrit_sweep(
+        caller: &Self::AccountId,
+        beneficiary: &Beneficiary,
+        outpoints: &Vec<H256>,
+    ) -> Result<Vec<H256>, DispatchError>;
+
+    /// Returns the SCALE-encoded output stored at `outpoint`, if it is unspent.
+    fn utxo(outpoint: &H256) -> Option<Vec<u8>>;
+
+    /// Returns the SCALE-encoded `TokenIssuanceV1` data of the token with `token_id`.
+    fn token_issuance(token_id: &H160) -> Option<Vec<u8>>;
+
+    /// Returns the current time in seconds, as used by time locks.
+    fn timestamp() -> u64;
+
+    /// Runs `call` with the outpoint of a `CallPP` UTXO paying `value` to the
+    /// `destination` contract, which nothing was spent for.
+    /// Returns what `call` returns, and the SCALE-encoded transactions made by
+    /// contracts meanwhile. Nothing is committed to storage, whatever `call` does.
+    fn dry_run<R>(
+        destination: &Self::AccountId,
+        value: u128,
+        fund_contract: bool,
+        data: &Vec<u8>,
+        gas_limit: Weight,
+        call: impl FnOnce(H256) -> R,
+    ) -> (R, Vec<Vec<u8>>);
 }